@@ -1,10 +1,10 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use easy_pow::{search, Hash, HashOutput, PowSearchParameters, ThreadConfig};
+use easy_pow::{search, Hash, HashOutput, PowSearchParameters, SearchOutcome, ThreadConfig};
 
-pub fn bench_hash_function_with_16bit_prefix_zero<H: Hash>(mut characters: Vec<Vec<u8>>) {
-    for i in 0..10 {
-        characters[i].clear();
-        characters[i].push(rand::random());
+pub fn bench_hash_function_with_16bit_prefix_zero<H: Hash + Default>(mut characters: Vec<Vec<u8>>) {
+    for c in characters.iter_mut().take(10) {
+        c.clear();
+        c.push(rand::random());
     }
     let target_hash = H::Output::zero();
     let mut target_hash_mask = H::Output::zero();
@@ -20,18 +20,17 @@ pub fn bench_hash_function_with_16bit_prefix_zero<H: Hash>(mut characters: Vec<V
         plaintext_character_map: characters,
     };
     let result = search(search_params, &ThreadConfig::default());
-    match result {
-        easy_pow::SearchResult::Found(result) => {
-            let digest = H::hash(&result);
-            let digest = digest.as_slice();
+    match result.map(|report| report.outcome) {
+        Ok(SearchOutcome::Found(solution)) => {
+            let digest = solution.digest.as_slice();
             assert_eq!(digest[0], 0);
             assert_eq!(digest[1], 0);
         }
-        easy_pow::SearchResult::NotFound => {
+        Ok(_) => {
             panic!("Not found")
         }
-        _ => {
-            panic!("Error")
+        Err(e) => {
+            panic!("{}", e)
         }
     }
 }
@@ -0,0 +1,365 @@
+//! Algebraic solver for hash functions that are affine over GF(2), such as CRCs.
+//!
+//! For a fixed input length, `hash(a ^ b ^ c) == hash(a) ^ hash(b) ^ hash(c)`
+//! holds for these functions, so the masked output bits are an affine function
+//! of the input bits and a matching input can be found by Gaussian elimination
+//! instead of enumeration.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::{
+    search_with_hasher, wait_for_done, Crc32, Crc64, Hash, HashOutput, Keyspace, OnDone,
+    OwnThreads, PowError, PowSearchParameters, Runner, SearchOutcome, SearchReport, Solution,
+    ThreadConfig,
+};
+
+/// Marker for hash functions that are affine over GF(2) for a fixed input length.
+pub trait AffineHash: Hash {}
+
+impl AffineHash for Crc32 {}
+impl AffineHash for Crc64 {}
+
+/// Same as [`search_with_hasher`], but solves the target as a linear system
+/// first.
+///
+/// Every position of `plaintext_character_map` contributes the bits of the
+/// largest subset of its alphabet of the form `base ^ span(bits)` as
+/// unknowns; a position that allows all 256 bytes contributes 8 bits, a
+/// printable ASCII position 5 bits and a digit position 3 bits. If the system
+/// has no solution within those subsets, this falls back to enumerating the
+/// whole plaintext space. The hashes of the linear solve are counted on the
+/// calling thread.
+///
+/// The linear solution does not depend on thread timing, but it is not the
+/// lexicographically smallest one even with [`ThreadConfig::deterministic`].
+pub fn search_affine<H: AffineHash>(
+    hasher: H,
+    params: PowSearchParameters<H>,
+    thread_config: &ThreadConfig,
+) -> Result<SearchReport<H::Output>, PowError> {
+    wait_for_done(|done| {
+        search_affine_with(
+            hasher,
+            params,
+            thread_config,
+            OwnThreads.worker_count(thread_config),
+            |hasher, params, thread_config, on_done| {
+                on_done(search_with_hasher(hasher, params, thread_config))
+            },
+            move |result| {
+                let _ = done.send(result);
+            },
+        )
+    })?
+}
+
+/// Same as [`search_affine`], but enumerates with `fallback`, which starts
+/// `worker_count` workers, and passes the result to `on_done`.
+pub(crate) fn search_affine_with<'a, H: AffineHash>(
+    hasher: H,
+    params: PowSearchParameters<H>,
+    thread_config: &ThreadConfig,
+    worker_count: usize,
+    fallback: impl FnOnce(H, PowSearchParameters<H>, &ThreadConfig, OnDone<'a, H::Output>),
+    on_done: impl FnOnce(Result<SearchReport<H::Output>, PowError>) + Send + 'a,
+) {
+    let start = Instant::now();
+    // Shaped like the reports of the engine: the calling thread, then the
+    // workers
+    let mut thread_hashes = vec![0; worker_count + 1];
+    if thread_config
+        .cancel
+        .as_ref()
+        .is_some_and(|cancel| cancel.is_cancelled())
+    {
+        return on_done(Ok(SearchReport::new(
+            SearchOutcome::Cancelled,
+            thread_hashes,
+            start.elapsed(),
+        )));
+    }
+    let mut hashes = 0;
+    let solved = solve_linear(&hasher, &params, &mut hashes);
+    if let Some(progress) = &thread_config.progress {
+        progress.add(hashes);
+    }
+    if let Some((plaintext, digest)) = solved {
+        let index = Keyspace::new(params.plaintext_character_map.clone())
+            .plaintext_to_index(&plaintext)
+            .expect("solutions come from the keyspace");
+        let solution = Solution::new(plaintext, digest, index);
+        thread_hashes[0] = hashes;
+        return on_done(Ok(SearchReport::new(
+            SearchOutcome::Found(solution),
+            thread_hashes,
+            start.elapsed(),
+        )));
+    }
+    fallback(
+        hasher,
+        params,
+        thread_config,
+        Box::new(move |result| {
+            on_done(result.map(|mut report| {
+                report.thread_hashes[0] += hashes;
+                report.total_hashes += hashes;
+                report.elapsed = start.elapsed();
+                report
+            }))
+        }),
+    )
+}
+
+fn get_bit(bytes: &[u8], i: usize) -> bool {
+    bytes[i / 8] >> (7 - i % 8) & 1 == 1
+}
+
+/// Returns `(base, mask)` with `base & mask == 0` such that `base ^ y` is in
+/// `alphabet` for every submask `y` of `mask`, maximizing the bits of `mask`.
+fn largest_subspace(alphabet: &[u8]) -> (u8, u8) {
+    let mut present = [false; 256];
+    for &c in alphabet {
+        present[c as usize] = true;
+    }
+    let count = present.iter().filter(|&&p| p).count();
+
+    let mut masks: Vec<u8> = (0..=255).collect();
+    masks.sort_by_key(|m| std::cmp::Reverse(m.count_ones()));
+    for mask in masks {
+        if (1 << mask.count_ones()) > count {
+            continue;
+        }
+        for &base in alphabet {
+            if base & mask != 0 {
+                continue;
+            }
+            let mut y = mask;
+            let contained = loop {
+                if !present[(base ^ y) as usize] {
+                    break false;
+                }
+                if y == 0 {
+                    break true;
+                }
+                y = (y - 1) & mask;
+            };
+            if contained {
+                return (base, mask);
+            }
+        }
+    }
+    unreachable!("a single character is always a subspace")
+}
+
+/// Returns a matching plaintext and its digest, adding the number of hashes
+/// computed to `hashes`.
+fn solve_linear<H: AffineHash>(
+    hasher: &H,
+    params: &PowSearchParameters<H>,
+    hashes: &mut u64,
+) -> Option<(Vec<u8>, H::Output)> {
+    let character_map = &params.plaintext_character_map;
+    if character_map.iter().any(|c| c.is_empty()) {
+        return None;
+    }
+
+    let mut subspace_cache = HashMap::new();
+    let subspaces: Vec<(u8, u8)> = character_map
+        .iter()
+        .map(|alphabet| {
+            *subspace_cache
+                .entry(alphabet.as_slice())
+                .or_insert_with(|| largest_subspace(alphabet))
+        })
+        .collect();
+    let base: Vec<u8> = subspaces.iter().map(|&(base, _)| base).collect();
+    let variables: Vec<(usize, u8)> = subspaces
+        .iter()
+        .enumerate()
+        .flat_map(|(pos, &(_, mask))| {
+            (0..8)
+                .map(move |bit| (pos, 1u8 << bit))
+                .filter(move |&(_, bit)| mask & bit != 0)
+        })
+        .collect();
+
+    let base_hash = hasher.hash(&base);
+    *hashes += 1 + variables.len() as u64;
+    let base_hash = base_hash.as_slice();
+    let target_hash = params.target_hash.as_slice();
+    let target_hash_mask = params.target_hash_mask.as_slice();
+    let output_bits: Vec<usize> = (0..target_hash_mask.len() * 8)
+        .filter(|&i| get_bit(target_hash_mask, i))
+        .collect();
+
+    // rows[r] holds the coefficients of the variables for the r-th masked output bit
+    let words = variables.len().div_ceil(64);
+    let mut rows = vec![vec![0u64; words]; output_bits.len()];
+    let mut rhs: Vec<bool> = output_bits
+        .iter()
+        .map(|&i| get_bit(target_hash, i) ^ get_bit(base_hash, i))
+        .collect();
+    let mut plaintext = base.clone();
+    for (v, &(pos, bit)) in variables.iter().enumerate() {
+        plaintext[pos] ^= bit;
+        let hash = hasher.hash(&plaintext);
+        plaintext[pos] ^= bit;
+        for (row, &i) in rows.iter_mut().zip(&output_bits) {
+            if get_bit(hash.as_slice(), i) != get_bit(base_hash, i) {
+                row[v / 64] |= 1 << (v % 64);
+            }
+        }
+    }
+
+    // Gauss-Jordan elimination
+    let mut pivots = Vec::new();
+    for v in 0..variables.len() {
+        let rank = pivots.len();
+        if rank == rows.len() {
+            break;
+        }
+        let Some(r) = (rank..rows.len()).find(|&r| rows[r][v / 64] >> (v % 64) & 1 == 1) else {
+            continue;
+        };
+        rows.swap(rank, r);
+        rhs.swap(rank, r);
+        let pivot_row = rows[rank].clone();
+        let pivot_rhs = rhs[rank];
+        for (r, (row, rhs)) in rows.iter_mut().zip(rhs.iter_mut()).enumerate() {
+            if r != rank && row[v / 64] >> (v % 64) & 1 == 1 {
+                for (w, p) in row.iter_mut().zip(&pivot_row) {
+                    *w ^= p;
+                }
+                *rhs ^= pivot_rhs;
+            }
+        }
+        pivots.push(v);
+    }
+    if rhs[pivots.len()..].iter().any(|&b| b) {
+        return None;
+    }
+
+    for (r, &v) in pivots.iter().enumerate() {
+        if rhs[r] {
+            let (pos, bit) = variables[v];
+            plaintext[pos] ^= bit;
+        }
+    }
+    // Guards against hash functions that are not actually affine
+    let digest = hasher.hash(&plaintext);
+    *hashes += 1;
+    params.check_hash(&digest).then_some((plaintext, digest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{largest_subspace, search_affine};
+    use crate::{
+        CancellationToken, Crc32, Crc64, Hash, PowSearchParameters, SearchOutcome, SearchReport,
+        ThreadConfig,
+    };
+
+    #[test]
+    fn test_largest_subspace() {
+        assert_eq!(largest_subspace(&(0..=255).collect::<Vec<u8>>()), (0, 0xff));
+        assert_eq!(largest_subspace(b"0123456789"), (b'0', 0x07));
+        assert_eq!(largest_subspace(b"x"), (b'x', 0));
+        let printable: Vec<u8> = (0x20..0x7f).collect();
+        assert_eq!(largest_subspace(&printable).1.count_ones(), 5);
+    }
+
+    #[test]
+    fn test_search_affine_crc() {
+        let thread_config = ThreadConfig {
+            thread_count: Some(2),
+            cancel: None,
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+        let mut plaintext_character_map: Vec<Vec<u8>> = b"flag{".iter().map(|&c| vec![c]).collect();
+        plaintext_character_map.extend(vec![(0x20..0x7f).collect(); 8]);
+        plaintext_character_map.push(vec![b'}']);
+
+        let params = PowSearchParameters::<Crc32> {
+            target_hash: 0xdeadbeefu32.to_be_bytes(),
+            target_hash_mask: [0xff; 4],
+            plaintext_character_map: plaintext_character_map.clone(),
+        };
+        match search_affine(Crc32::default(), params.clone(), &thread_config) {
+            Ok(SearchReport {
+                outcome: SearchOutcome::Found(solution),
+                thread_hashes,
+                ..
+            }) => {
+                assert_eq!(thread_hashes.len(), 3);
+                assert_eq!(
+                    Crc32::default().hash(&solution.plaintext),
+                    0xdeadbeefu32.to_be_bytes()
+                );
+                assert!(
+                    solution.plaintext.starts_with(b"flag{") && solution.plaintext.ends_with(b"}")
+                );
+                assert!(solution.plaintext.iter().all(|c| (0x20..0x7f).contains(c)));
+            }
+            _ => panic!("Unexpected result"),
+        }
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let cancelled = ThreadConfig {
+            cancel: Some(cancel),
+            ..thread_config.clone()
+        };
+        let report = search_affine(Crc32::default(), params, &cancelled).unwrap();
+        assert_eq!(report.outcome, SearchOutcome::Cancelled);
+        assert_eq!(report.thread_hashes, [0; 3]);
+
+        plaintext_character_map.extend(vec![(0x20..0x7f).collect(); 6]);
+        let params = PowSearchParameters::<Crc64> {
+            target_hash: 0x0123456789abcdefu64.to_be_bytes(),
+            target_hash_mask: [0xff; 8],
+            plaintext_character_map,
+        };
+        match search_affine(Crc64::default(), params, &thread_config) {
+            Ok(SearchReport {
+                outcome: SearchOutcome::Found(solution),
+                ..
+            }) => {
+                assert_eq!(
+                    Crc64::default().hash(&solution.plaintext),
+                    0x0123456789abcdefu64.to_be_bytes()
+                );
+            }
+            _ => panic!("Unexpected result"),
+        }
+    }
+
+    #[test]
+    fn test_search_affine_fallback() {
+        // Two digits give only 6 unknowns for 8 target bits, and "99" lies
+        // outside of the 0x30..=0x37 subspace
+        let target = Crc32::default().hash(b"99");
+        let params = PowSearchParameters::<Crc32> {
+            target_hash: target,
+            target_hash_mask: [0xff, 0, 0, 0],
+            plaintext_character_map: vec![b"0123456789".to_vec(); 2],
+        };
+        let thread_config = ThreadConfig {
+            thread_count: Some(2),
+            cancel: None,
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+        match search_affine(Crc32::default(), params, &thread_config) {
+            Ok(SearchReport {
+                outcome: SearchOutcome::Found(solution),
+                ..
+            }) => {
+                assert_eq!(Crc32::default().hash(&solution.plaintext)[0], target[0])
+            }
+            _ => panic!("Unexpected result"),
+        }
+    }
+}
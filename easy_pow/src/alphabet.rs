@@ -0,0 +1,201 @@
+use crate::Wordlist;
+
+/// The choices for one position of a plaintext.
+///
+/// A `Vec<u8>` offers single bytes, which searches write in place. A
+/// [`Tokens`] offers byte strings of any length, such as UTF-8 characters or
+/// whole words, so plaintexts of one keyspace may differ in length. Tokens
+/// may also be computed, like the words of a [`Wordlist`] with rules applied.
+pub trait Alphabet: Send + Sync {
+    /// Whether every token is exactly one byte long.
+    const SINGLE_BYTE: bool = false;
+
+    /// The number of tokens.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends the token at `index`, which must be below
+    /// [`len`](Self::len), to `plaintext`.
+    fn push_token(&self, index: usize, plaintext: &mut Vec<u8>);
+
+    /// The token at `index` of a [`SINGLE_BYTE`](Self::SINGLE_BYTE)
+    /// alphabet.
+    fn byte(&self, index: usize) -> u8 {
+        let mut token = Vec::with_capacity(1);
+        self.push_token(index, &mut token);
+        token[0]
+    }
+
+    /// The tokens as single bytes, or `None` if a token is not one byte long.
+    fn single_bytes(&self) -> Option<Vec<u8>> {
+        let mut token = Vec::new();
+        (0..self.len())
+            .map(|i| {
+                token.clear();
+                self.push_token(i, &mut token);
+                match token[..] {
+                    [c] => Some(c),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Alphabet for Vec<u8> {
+    const SINGLE_BYTE: bool = true;
+
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn push_token(&self, index: usize, plaintext: &mut Vec<u8>) {
+        plaintext.push(self[index]);
+    }
+
+    #[inline]
+    fn byte(&self, index: usize) -> u8 {
+        self[index]
+    }
+
+    fn single_bytes(&self) -> Option<Vec<u8>> {
+        Some(self.clone())
+    }
+}
+
+/// An [`Alphabet`] of byte strings, stored back to back.
+///
+/// Tokens may be empty, which makes a position optional.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tokens {
+    bytes: Vec<u8>,
+    /// `ends[i]` is where token `i` ends in `bytes`.
+    ends: Vec<usize>,
+}
+
+impl Tokens {
+    pub fn new<T: AsRef<[u8]>>(tokens: impl IntoIterator<Item = T>) -> Self {
+        tokens.into_iter().collect()
+    }
+
+    /// One token per byte of `chars`.
+    pub fn from_bytes(chars: impl AsRef<[u8]>) -> Self {
+        Self {
+            bytes: chars.as_ref().to_vec(),
+            ends: (1..=chars.as_ref().len()).collect(),
+        }
+    }
+
+    pub fn push(&mut self, token: impl AsRef<[u8]>) {
+        self.bytes.extend_from_slice(token.as_ref());
+        self.ends.push(self.bytes.len());
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        let end = *self.ends.get(index)?;
+        let start = index.checked_sub(1).map_or(0, |i| self.ends[i]);
+        Some(&self.bytes[start..end])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        (0..self.ends.len()).map(|i| self.get(i).expect("index is in range"))
+    }
+}
+
+impl<T: AsRef<[u8]>> FromIterator<T> for Tokens {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tokens = Self::default();
+        for token in iter {
+            tokens.push(token);
+        }
+        tokens
+    }
+}
+
+impl Alphabet for Tokens {
+    fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    #[inline]
+    fn push_token(&self, index: usize, plaintext: &mut Vec<u8>) {
+        plaintext.extend_from_slice(self.get(index).expect("token index out of range"));
+    }
+}
+
+/// An [`Alphabet`] of any kind, for plaintext_character_maps that mix them,
+/// such as a [`Wordlist`] followed by the positions of a mask.
+#[derive(Debug, Clone)]
+pub enum AnyAlphabet {
+    Bytes(Vec<u8>),
+    Tokens(Tokens),
+    Wordlist(Wordlist),
+}
+
+impl Alphabet for AnyAlphabet {
+    fn len(&self) -> usize {
+        match self {
+            Self::Bytes(bytes) => bytes.len(),
+            Self::Tokens(tokens) => tokens.len(),
+            Self::Wordlist(wordlist) => wordlist.len(),
+        }
+    }
+
+    #[inline]
+    fn push_token(&self, index: usize, plaintext: &mut Vec<u8>) {
+        match self {
+            Self::Bytes(bytes) => bytes.push_token(index, plaintext),
+            Self::Tokens(tokens) => tokens.push_token(index, plaintext),
+            Self::Wordlist(wordlist) => wordlist.push_token(index, plaintext),
+        }
+    }
+}
+
+impl From<Vec<u8>> for AnyAlphabet {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Bytes(bytes)
+    }
+}
+
+impl From<Tokens> for AnyAlphabet {
+    fn from(tokens: Tokens) -> Self {
+        Self::Tokens(tokens)
+    }
+}
+
+impl From<Wordlist> for AnyAlphabet {
+    fn from(wordlist: Wordlist) -> Self {
+        Self::Wordlist(wordlist)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Alphabet, AnyAlphabet, Tokens};
+
+    #[test]
+    fn test_tokens() {
+        let tokens = Tokens::new(["admin", "", "ルート"]);
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens.get(0), Some(&b"admin"[..]));
+        assert_eq!(tokens.get(1), Some(&b""[..]));
+        assert_eq!(tokens.get(3), None);
+        let mut plaintext = b"x".to_vec();
+        tokens.push_token(2, &mut plaintext);
+        assert_eq!(plaintext, "xルート".as_bytes());
+        assert_eq!(tokens.iter().count(), 3);
+        assert_eq!(tokens.single_bytes(), None);
+
+        let tokens = Tokens::from_bytes("xyz");
+        assert_eq!(tokens.byte(1), b'y');
+        assert_eq!(tokens.single_bytes(), Some(b"xyz".to_vec()));
+        assert!(!<Vec<u8> as Alphabet>::is_empty(&b"a".to_vec()));
+        assert_eq!(
+            AnyAlphabet::from(tokens).single_bytes(),
+            Some(b"xyz".to_vec())
+        );
+    }
+}
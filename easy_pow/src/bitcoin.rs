@@ -0,0 +1,336 @@
+//! Bitcoin block header nonce search.
+//!
+//! Hashes are in internal byte order (as serialized); the usual hex display
+//! of a block hash is the reversed byte string.
+
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{search_with_hasher, Hash, PowError, PowSearchParameters, ThreadConfig};
+
+/// Converts a compact `nBits` value to a 256-bit big-endian target.
+///
+/// Returns `None` for negative, zero or overflowing targets.
+pub fn compact_to_target(bits: u32) -> Option<[u8; 32]> {
+    let exponent = (bits >> 24) as usize;
+    let mantissa = bits & 0x007f_ffff;
+    if bits & 0x0080_0000 != 0 || mantissa == 0 {
+        return None;
+    }
+    let mut target = [0u8; 32];
+    for (i, &b) in mantissa.to_be_bytes()[1..].iter().enumerate() {
+        // byte i of the mantissa has the weight 256^(exponent - 1 - i)
+        let weight = (exponent + 2).checked_sub(3 + i);
+        match weight {
+            Some(weight) if weight < 32 => target[31 - weight] = b,
+            Some(_) if b != 0 => return None,
+            _ => {}
+        }
+    }
+    if target.iter().all(|&b| b == 0) {
+        return None;
+    }
+    Some(target)
+}
+
+/// Converts a 256-bit big-endian target to the compact `nBits` encoding.
+pub fn target_to_compact(target: &[u8; 32]) -> u32 {
+    let Some(first) = target.iter().position(|&b| b != 0) else {
+        return 0;
+    };
+    let mut size = 32 - first;
+    let mut mantissa = [0u8; 4];
+    for i in 0..3 {
+        mantissa[i + 1] = *target.get(first + i).unwrap_or(&0);
+    }
+    let mut mantissa = u32::from_be_bytes(mantissa);
+    if mantissa & 0x0080_0000 != 0 {
+        mantissa >>= 8;
+        size += 1;
+    }
+    (size as u32) << 24 | mantissa
+}
+
+fn double_sha256(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(bytes)).into()
+}
+
+/// `hash` (internal byte order) read as a little-endian number is at most `target`.
+fn meets_target(hash: &[u8; 32], target: &[u8; 32]) -> bool {
+    hash.iter().rev().le(target.iter())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub version: i32,
+    pub prev_block_hash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+impl BlockHeader {
+    pub fn to_bytes(&self) -> [u8; 80] {
+        let mut ret = [0u8; 80];
+        ret[0..4].copy_from_slice(&self.version.to_le_bytes());
+        ret[4..36].copy_from_slice(&self.prev_block_hash);
+        ret[36..68].copy_from_slice(&self.merkle_root);
+        ret[68..72].copy_from_slice(&self.time.to_le_bytes());
+        ret[72..76].copy_from_slice(&self.bits.to_le_bytes());
+        ret[76..80].copy_from_slice(&self.nonce.to_le_bytes());
+        ret
+    }
+
+    pub fn from_bytes(bytes: &[u8; 80]) -> Self {
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        Self {
+            version: u32_at(0) as i32,
+            prev_block_hash: bytes[4..36].try_into().unwrap(),
+            merkle_root: bytes[36..68].try_into().unwrap(),
+            time: u32_at(68),
+            bits: u32_at(72),
+            nonce: u32_at(76),
+        }
+    }
+
+    /// The double SHA-256 of the header in internal byte order.
+    pub fn hash(&self) -> [u8; 32] {
+        double_sha256(&self.to_bytes())
+    }
+
+    /// Whether the header hash satisfies its own `bits`.
+    pub fn is_valid_pow(&self) -> bool {
+        compact_to_target(self.bits).is_some_and(|target| meets_target(&self.hash(), &target))
+    }
+}
+
+/// The coinbase transaction split around its extranonce, as in Stratum's
+/// `mining.notify`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coinbase {
+    pub prefix: Vec<u8>,
+    pub suffix: Vec<u8>,
+    /// The size in bytes (at most 8) of the little-endian extranonce.
+    pub extranonce_size: usize,
+    /// The hashes paired with the coinbase on its way up to the merkle root.
+    pub merkle_branch: Vec<[u8; 32]>,
+}
+
+impl Coinbase {
+    pub fn merkle_root(&self, extranonce: u64) -> [u8; 32] {
+        let mut tx = self.prefix.clone();
+        tx.extend_from_slice(&extranonce.to_le_bytes()[..self.extranonce_size]);
+        tx.extend_from_slice(&self.suffix);
+        let mut root = double_sha256(&tx);
+        for branch in &self.merkle_branch {
+            let mut pair = [0u8; 64];
+            pair[..32].copy_from_slice(&root);
+            pair[32..].copy_from_slice(branch);
+            root = double_sha256(&pair);
+        }
+        root
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockTemplate {
+    pub version: i32,
+    pub prev_block_hash: [u8; 32],
+    /// Used when `coinbase` is `None`.
+    pub merkle_root: [u8; 32],
+    pub time: u32,
+    pub bits: u32,
+    /// When given, the extranonce is rolled once the time range is exhausted.
+    pub coinbase: Option<Coinbase>,
+    /// How far `time` may be increased when the nonce space runs out.
+    pub max_time_roll: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinedBlock {
+    pub header: BlockHeader,
+    /// The extranonce used in the coinbase, if any.
+    pub extranonce: Option<u64>,
+    pub hash: [u8; 32],
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum MiningError {
+    #[error("bits is not a valid compact target")]
+    InvalidBits,
+    #[error("extranonce_size must be at most 8")]
+    InvalidExtranonceSize,
+    #[error(transparent)]
+    Search(#[from] PowError),
+}
+
+/// Hashes the last 16 bytes of a header from the midstate of the first 64
+/// bytes. The output is `[1]` when the header meets the target and `[0]`
+/// otherwise, so a search for `[1]` finds a valid nonce.
+#[derive(Clone)]
+struct HeaderTailHash {
+    midstate: Sha256,
+    target: [u8; 32],
+}
+
+impl Hash for HeaderTailHash {
+    type Output = [u8; 1];
+    const PARALLEL_BLOCK_SIZE: usize = 4096;
+
+    fn hash(&self, bytes: &[u8]) -> [u8; 1] {
+        let mut hasher = self.midstate.clone();
+        hasher.update(bytes);
+        let hash: [u8; 32] = Sha256::digest(hasher.finalize()).into();
+        [meets_target(&hash, &self.target) as u8]
+    }
+}
+
+/// Searches the nonce of `template`, rolling the time and then the
+/// extranonce whenever the 32-bit nonce space is exhausted.
+///
+/// Returns `Ok(None)` if the whole space was searched.
+pub fn mine(
+    template: &BlockTemplate,
+    thread_config: &ThreadConfig,
+) -> Result<Option<MinedBlock>, MiningError> {
+    let Some(target) = compact_to_target(template.bits) else {
+        return Err(MiningError::InvalidBits);
+    };
+    let extranonces: Box<dyn Iterator<Item = Option<u64>>> = match &template.coinbase {
+        Some(coinbase) if coinbase.extranonce_size > 8 => {
+            return Err(MiningError::InvalidExtranonceSize)
+        }
+        Some(coinbase) if coinbase.extranonce_size == 8 => Box::new((0..=u64::MAX).map(Some)),
+        Some(coinbase) => Box::new((0..1u64 << (8 * coinbase.extranonce_size)).map(Some)),
+        None => Box::new(std::iter::once(None)),
+    };
+
+    for extranonce in extranonces {
+        let merkle_root = match (&template.coinbase, extranonce) {
+            (Some(coinbase), Some(extranonce)) => coinbase.merkle_root(extranonce),
+            _ => template.merkle_root,
+        };
+        let mut header = BlockHeader {
+            version: template.version,
+            prev_block_hash: template.prev_block_hash,
+            merkle_root,
+            time: template.time,
+            bits: template.bits,
+            nonce: 0,
+        };
+        let midstate = Sha256::new().chain_update(&header.to_bytes()[..64]);
+
+        for time_roll in 0..=template.max_time_roll {
+            let Some(time) = template.time.checked_add(time_roll) else {
+                break;
+            };
+            header.time = time;
+            let mut plaintext_character_map: Vec<Vec<u8>> =
+                header.to_bytes()[64..76].iter().map(|&b| vec![b]).collect();
+            plaintext_character_map.extend(vec![(0..=255).collect(); 4]);
+            let params = PowSearchParameters {
+                target_hash: [1],
+                target_hash_mask: [0xff],
+                plaintext_character_map,
+            };
+            let hasher = HeaderTailHash {
+                midstate: midstate.clone(),
+                target,
+            };
+            if let Some(tail) =
+                search_with_hasher(hasher, params, thread_config)?.into_solution()?
+            {
+                let tail = tail.plaintext;
+                header.nonce = u32::from_le_bytes(tail[12..16].try_into().unwrap());
+                return Ok(Some(MinedBlock {
+                    header,
+                    extranonce,
+                    hash: header.hash(),
+                }));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compact_to_target, mine, target_to_compact, BlockHeader, BlockTemplate, Coinbase};
+    use crate::ThreadConfig;
+
+    fn genesis_header() -> BlockHeader {
+        let mut merkle_root = [0u8; 32];
+        for (i, b) in merkle_root.iter_mut().enumerate() {
+            let hex = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+            *b = u8::from_str_radix(&hex[(31 - i) * 2..(32 - i) * 2], 16).unwrap();
+        }
+        BlockHeader {
+            version: 1,
+            prev_block_hash: [0; 32],
+            merkle_root,
+            time: 1231006505,
+            bits: 0x1d00ffff,
+            nonce: 2083236893,
+        }
+    }
+
+    #[test]
+    fn test_genesis_block() {
+        let header = genesis_header();
+        let mut hash = header.hash();
+        hash.reverse();
+        assert_eq!(hash[..8], [0x00, 0x00, 0x00, 0x00, 0x00, 0x19, 0xd6, 0x68]);
+        assert!(header.is_valid_pow());
+        assert_eq!(BlockHeader::from_bytes(&header.to_bytes()), header);
+
+        let target = compact_to_target(0x1d00ffff).unwrap();
+        assert_eq!(target[..6], [0, 0, 0, 0, 0xff, 0xff]);
+        assert!(target[6..].iter().all(|&b| b == 0));
+        assert_eq!(target_to_compact(&target), 0x1d00ffff);
+        assert_eq!(compact_to_target(0x04923456), None);
+        assert_eq!(compact_to_target(0x01003456), None);
+        assert_eq!(compact_to_target(0x23000001), None);
+    }
+
+    #[test]
+    fn test_mine() {
+        let genesis = genesis_header();
+        let template = BlockTemplate {
+            version: 0x20000000,
+            prev_block_hash: genesis.hash(),
+            merkle_root: [0; 32],
+            time: genesis.time + 600,
+            bits: 0x1f00ffff,
+            coinbase: Some(Coinbase {
+                prefix: b"coinbase prefix".to_vec(),
+                suffix: b"coinbase suffix".to_vec(),
+                extranonce_size: 4,
+                merkle_branch: vec![[0x11; 32], [0x22; 32]],
+            }),
+            max_time_roll: 60,
+        };
+        let thread_config = ThreadConfig {
+            thread_count: Some(2),
+            cancel: None,
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+        match mine(&template, &thread_config) {
+            Ok(Some(mined)) => {
+                assert!(mined.header.is_valid_pow());
+                assert_eq!(mined.hash, mined.header.hash());
+                assert_eq!(
+                    mined.header.merkle_root,
+                    template
+                        .coinbase
+                        .as_ref()
+                        .unwrap()
+                        .merkle_root(mined.extranonce.unwrap())
+                );
+            }
+            _ => panic!("Unexpected result"),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// A flag to cancel searches from another thread.
+///
+/// Clones share the same flag. A token made with [`child`](Self::child) is
+/// also cancelled when its parent is, but cancelling the child leaves the
+/// parent untouched.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    parent: Option<CancellationToken>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A new token that is cancelled together with `self`.
+    pub fn child(&self) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                cancelled: AtomicBool::new(false),
+                parent: Some(self.clone()),
+            }),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Clears the cancellation of this token so that it can be reused. A
+    /// cancelled parent still cancels it.
+    pub fn reset(&self) {
+        self.inner.cancelled.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Relaxed)
+            || self
+                .inner
+                .parent
+                .as_ref()
+                .is_some_and(|parent| parent.is_cancelled())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CancellationToken;
+
+    #[test]
+    fn test_parent_and_child() {
+        let parent = CancellationToken::new();
+        let child = parent.child();
+        let clone = child.clone();
+
+        clone.cancel();
+        assert!(child.is_cancelled());
+        assert!(!parent.is_cancelled());
+        child.reset();
+        assert!(!clone.is_cancelled());
+
+        parent.cancel();
+        assert!(child.is_cancelled());
+        child.reset();
+        assert!(child.is_cancelled());
+        parent.reset();
+        assert!(!child.is_cancelled());
+    }
+}
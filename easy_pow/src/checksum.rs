@@ -0,0 +1,243 @@
+//! Non-cryptographic checksums.
+//!
+//! The digests are the big-endian bytes of the checksum value, so that a hex
+//! prefix such as `deadbeef` matches the usual printed form.
+
+use crate::Hash;
+
+/// CRC-32 (IEEE 802.3, as in zlib and PNG).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Crc32 {}
+
+impl Hash for Crc32 {
+    type Output = [u8; 4];
+    const PARALLEL_BLOCK_SIZE: usize = 1 << 16;
+
+    fn hash(&self, bytes: &[u8]) -> [u8; 4] {
+        crc32fast::hash(bytes).to_be_bytes()
+    }
+}
+
+const fn crc64_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u64;
+        let mut j = 0;
+        while j < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xc96c5795d7870f42
+            } else {
+                crc >> 1
+            };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+static CRC64_TABLE: [u64; 256] = crc64_table();
+
+/// CRC-64/XZ (ECMA-182 polynomial, reflected, as in xz).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Crc64 {}
+
+impl Hash for Crc64 {
+    type Output = [u8; 8];
+    const PARALLEL_BLOCK_SIZE: usize = 1 << 16;
+
+    fn hash(&self, bytes: &[u8]) -> [u8; 8] {
+        let mut crc = !0u64;
+        for &b in bytes {
+            crc = CRC64_TABLE[((crc as u8) ^ b) as usize] ^ (crc >> 8);
+        }
+        (!crc).to_be_bytes()
+    }
+}
+
+/// Adler-32 (as in zlib).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Adler32 {}
+
+impl Hash for Adler32 {
+    type Output = [u8; 4];
+    const PARALLEL_BLOCK_SIZE: usize = 1 << 17;
+
+    fn hash(&self, bytes: &[u8]) -> [u8; 4] {
+        adler::adler32_slice(bytes).to_be_bytes()
+    }
+}
+
+/// XXH32 with the given seed.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct XxHash32 {
+    pub seed: u32,
+}
+
+impl Hash for XxHash32 {
+    type Output = [u8; 4];
+    const PARALLEL_BLOCK_SIZE: usize = 1 << 16;
+
+    fn hash(&self, bytes: &[u8]) -> [u8; 4] {
+        xxhash_rust::xxh32::xxh32(bytes, self.seed).to_be_bytes()
+    }
+}
+
+/// XXH64 with the given seed.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct XxHash64 {
+    pub seed: u64,
+}
+
+impl Hash for XxHash64 {
+    type Output = [u8; 8];
+    const PARALLEL_BLOCK_SIZE: usize = 1 << 16;
+
+    fn hash(&self, bytes: &[u8]) -> [u8; 8] {
+        xxhash_rust::xxh64::xxh64(bytes, self.seed).to_be_bytes()
+    }
+}
+
+/// 32-bit FNV-1a.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Fnv1a32 {}
+
+impl Hash for Fnv1a32 {
+    type Output = [u8; 4];
+    const PARALLEL_BLOCK_SIZE: usize = 1 << 17;
+
+    fn hash(&self, bytes: &[u8]) -> [u8; 4] {
+        let mut h: u32 = 0x811c9dc5;
+        for &b in bytes {
+            h ^= b as u32;
+            h = h.wrapping_mul(0x01000193);
+        }
+        h.to_be_bytes()
+    }
+}
+
+/// 64-bit FNV-1a.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Fnv1a64 {}
+
+impl Hash for Fnv1a64 {
+    type Output = [u8; 8];
+    const PARALLEL_BLOCK_SIZE: usize = 1 << 17;
+
+    fn hash(&self, bytes: &[u8]) -> [u8; 8] {
+        let mut h: u64 = 0xcbf29ce484222325;
+        for &b in bytes {
+            h ^= b as u64;
+            h = h.wrapping_mul(0x100000001b3);
+        }
+        h.to_be_bytes()
+    }
+}
+
+/// MurmurHash3 (x86, 32-bit) with the given seed.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Murmur3 {
+    pub seed: u32,
+}
+
+impl Hash for Murmur3 {
+    type Output = [u8; 4];
+    const PARALLEL_BLOCK_SIZE: usize = 1 << 16;
+
+    fn hash(&self, bytes: &[u8]) -> [u8; 4] {
+        const C1: u32 = 0xcc9e2d51;
+        const C2: u32 = 0x1b873593;
+
+        let mut h = self.seed;
+        let mut blocks = bytes.chunks_exact(4);
+        for block in &mut blocks {
+            let mut k = u32::from_le_bytes(block.try_into().unwrap());
+            k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+            h ^= k;
+            h = h.rotate_left(13).wrapping_mul(5).wrapping_add(0xe6546b64);
+        }
+        let tail = blocks.remainder();
+        if !tail.is_empty() {
+            let mut k = 0u32;
+            for (i, &b) in tail.iter().enumerate() {
+                k |= (b as u32) << (8 * i);
+            }
+            h ^= k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        }
+
+        h ^= bytes.len() as u32;
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x85ebca6b);
+        h ^= h >> 13;
+        h = h.wrapping_mul(0xc2b2ae35);
+        h ^= h >> 16;
+        h.to_be_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Adler32, Crc32, Crc64, Fnv1a32, Fnv1a64, Murmur3, XxHash32, XxHash64};
+    use crate::{search_by_hash_type, Hash, HashType, ThreadConfig};
+
+    #[test]
+    fn test_checksum_vectors() {
+        assert_eq!(
+            Crc32::default().hash(b"123456789"),
+            0xcbf43926u32.to_be_bytes()
+        );
+        assert_eq!(
+            Crc64::default().hash(b"123456789"),
+            0x995dc9bbdf1939fau64.to_be_bytes()
+        );
+        assert_eq!(
+            Adler32::default().hash(b"Wikipedia"),
+            0x11e60398u32.to_be_bytes()
+        );
+        assert_eq!(XxHash32::default().hash(b""), 0x02cc5d05u32.to_be_bytes());
+        assert_eq!(
+            XxHash64::default().hash(b""),
+            0xef46db3751d8e999u64.to_be_bytes()
+        );
+        assert_eq!(Fnv1a32::default().hash(b"a"), 0xe40c292cu32.to_be_bytes());
+        assert_eq!(
+            Fnv1a64::default().hash(b"a"),
+            0xaf63dc4c8601ec8cu64.to_be_bytes()
+        );
+        assert_eq!(Murmur3 { seed: 1 }.hash(b""), 0x514e28b7u32.to_be_bytes());
+        assert_eq!(
+            Murmur3::default().hash(b"The quick brown fox jumps over the lazy dog"),
+            0x2e4ff723u32.to_be_bytes()
+        );
+    }
+
+    #[test]
+    fn test_search_crc32_prefix() {
+        let thread_config = ThreadConfig {
+            thread_count: Some(2),
+            cancel: None,
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+        match search_by_hash_type(
+            "crc32".parse::<HashType>().unwrap(),
+            b"\xca\xfe\x00\x00",
+            b"\xff\xff\x00\x00",
+            &vec![(b'a'..=b'z').collect::<Vec<u8>>(); 5],
+            &thread_config,
+        ) {
+            Ok(report) => {
+                let solution = report.solution().expect("Unexpected not found");
+                assert_eq!(solution.digest[..2], [0xca, 0xfe]);
+                assert_eq!(
+                    Crc32::default().hash(&solution.plaintext)[..2],
+                    [0xca, 0xfe]
+                )
+            }
+            _ => panic!("Unexpected result"),
+        }
+    }
+}
@@ -0,0 +1,386 @@
+//! Collision search on the masked bits of a hash.
+//!
+//! This is the parallel collision search of van Oorschot and Wiener. The
+//! masked hash bits of a plaintext select the next plaintext, and every
+//! thread walks such trails from random starting points until it reaches a
+//! distinguished point, whose low bits are zero. Two trails that end at the
+//! same distinguished point have merged, and walking them again in lockstep
+//! finds the two plaintexts where they met. Only the endpoints are stored, so
+//! memory stays bounded.
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::{Hash, HashError, HashOutput, PowError, ThreadConfig};
+
+#[derive(Debug, Clone)]
+pub struct CollisionParameters<H: Hash> {
+    /// The bits that must agree between the two digests.
+    pub hash_mask: H::Output,
+
+    pub plaintext_character_map: Vec<Vec<u8>>,
+    /// Trails end at points whose lowest `distinguished_bits` masked bits are
+    /// zero. `None` picks a value that stores a few thousand points.
+    pub distinguished_bits: Option<u32>,
+}
+
+/// Two different plaintexts whose digests agree on the masked bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    pub first: Vec<u8>,
+    pub second: Vec<u8>,
+}
+
+/// Walks the function `state -> masked bits of hash(plaintext(state))`.
+struct Walker<'a, H: Hash> {
+    hasher: &'a H,
+    plaintext_character_map: &'a [Vec<u8>],
+    mask_bits: &'a [usize],
+    plaintext: Vec<u8>,
+}
+
+impl<H: Hash> Walker<'_, H> {
+    /// Reads `state` as a mixed-radix number with one digit per position.
+    fn set_plaintext(&mut self, mut state: u128) {
+        for (c, chars) in self.plaintext.iter_mut().zip(self.plaintext_character_map) {
+            let radix = chars.len() as u128;
+            *c = chars[(state % radix) as usize];
+            state /= radix;
+        }
+    }
+
+    fn step(&mut self, state: u128) -> Result<u128, HashError> {
+        self.set_plaintext(state);
+        let hash = self.hasher.try_hash(&self.plaintext)?;
+        let hash = hash.as_slice();
+        let mut next = 0;
+        for (j, &i) in self.mask_bits.iter().enumerate() {
+            next |= ((hash[i / 8] >> (7 - i % 8) & 1) as u128) << j;
+        }
+        Ok(next)
+    }
+
+    /// Finds where two trails ending at the same distinguished point merge.
+    ///
+    /// Returns `None` if one start lies on the other trail, or if the merge
+    /// comes from two states that read as the same plaintext.
+    fn locate(
+        &mut self,
+        (mut a, mut a_len): (u128, u64),
+        (mut b, b_len): (u128, u64),
+    ) -> Result<Option<Collision>, HashError> {
+        if a_len < b_len {
+            return self.locate((b, b_len), (a, a_len));
+        }
+        while a_len > b_len {
+            a = self.step(a)?;
+            a_len -= 1;
+        }
+        if a == b {
+            return Ok(None);
+        }
+        loop {
+            let (next_a, next_b) = (self.step(a)?, self.step(b)?);
+            if next_a == next_b {
+                self.set_plaintext(a);
+                let first = self.plaintext.clone();
+                self.set_plaintext(b);
+                return Ok((first != self.plaintext).then(|| Collision {
+                    first,
+                    second: self.plaintext.clone(),
+                }));
+            }
+            (a, b) = (next_a, next_b);
+        }
+    }
+}
+
+fn splitmix64(x: &mut u64) -> u64 {
+    *x = x.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *x;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Searches two different plaintexts whose digests agree on
+/// `params.hash_mask`. The mask must select between 1 and 128 bits.
+///
+/// The masked bits are read as an index into the plaintext space, so the
+/// space should have at least `2^(masked bits)` plaintexts; otherwise some
+/// merges are false alarms and a collision may not exist at all. The
+/// search gives up with `Ok(None)` after hashing many times the number of
+/// candidates a collision is expected to take, and returns
+/// [`PowError::Cancelled`] if `thread_config.cancel` is cancelled first.
+pub fn search_collision<H: Hash>(
+    hasher: H,
+    params: CollisionParameters<H>,
+    thread_config: &ThreadConfig,
+) -> Result<Option<Collision>, PowError> {
+    let hash_mask = params.hash_mask.as_slice();
+    let mask_bits: Vec<usize> = (0..hash_mask.len() * 8)
+        .filter(|&i| hash_mask[i / 8] >> (7 - i % 8) & 1 == 1)
+        .collect();
+    if mask_bits.is_empty() || mask_bits.len() > 128 {
+        return Err(PowError::CollisionMaskBits {
+            bits: mask_bits.len(),
+        });
+    }
+    if let Some(position) = params
+        .plaintext_character_map
+        .iter()
+        .position(|c| c.is_empty())
+    {
+        return Err(PowError::EmptyAlphabet { position });
+    }
+    let keyspace = params
+        .plaintext_character_map
+        .iter()
+        .try_fold(1u128, |n, chars| n.checked_mul(chars.len() as u128));
+    if keyspace.is_some_and(|n| n < 2) {
+        return Err(PowError::CollisionKeyspace);
+    }
+    let state_mask = u128::MAX >> (128 - mask_bits.len());
+    let distinguished_bits = params
+        .distinguished_bits
+        .unwrap_or((mask_bits.len() as u32 / 2).saturating_sub(12))
+        .min(mask_bits.len() as u32)
+        .min(48);
+    let distinguished_mask = (1u128 << distinguished_bits) - 1;
+    let max_trail_len = 20u64 << distinguished_bits;
+
+    let thread_count = thread_config
+        .thread_count
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|a| a.get())
+                .unwrap_or(2)
+        })
+        .max(1);
+    // A collision takes about 2^(masked bits / 2) steps, plus one trail per
+    // thread past it to reach a distinguished point. Allow many times that
+    // before concluding that the collisions found are all false alarms.
+    let expected_steps = (1u64 << (mask_bits.len() / 2).min(56))
+        .saturating_add((thread_count as u64).saturating_mul(max_trail_len));
+    let max_steps = expected_steps.saturating_mul(64);
+    let steps = AtomicU64::new(0);
+    let seed = RandomState::new().build_hasher().finish();
+    let search_end = AtomicBool::new(false);
+    let is_cancelled = || {
+        search_end.load(Ordering::Relaxed)
+            || thread_config
+                .cancel
+                .as_ref()
+                .is_some_and(|cancel| cancel.is_cancelled())
+    };
+    // distinguished point -> (start, trail length)
+    let points: Mutex<HashMap<u128, (u128, u64)>> = Mutex::new(HashMap::new());
+    let result = Mutex::new(None);
+
+    thread::scope(|s| {
+        let mut workers = Vec::with_capacity(thread_count);
+        for thread_index in 0..thread_count {
+            let (hasher, params, mask_bits) = (&hasher, &params, &mask_bits);
+            let (points, result, search_end, steps) = (&points, &result, &search_end, &steps);
+            let is_cancelled = &is_cancelled;
+            let walk = move || -> Result<(), HashError> {
+                let mut walker = Walker {
+                    hasher,
+                    plaintext_character_map: &params.plaintext_character_map,
+                    mask_bits,
+                    plaintext: vec![0; params.plaintext_character_map.len()],
+                };
+                let mut rng = seed ^ (thread_index as u64).wrapping_mul(0xd1b54a32d192ed03);
+                'trail: while !is_cancelled() {
+                    let start = ((splitmix64(&mut rng) as u128) << 64
+                        | splitmix64(&mut rng) as u128)
+                        & state_mask;
+                    let mut state = start;
+                    let mut len = 0;
+                    let distinguished = loop {
+                        state = walker.step(state)?;
+                        len += 1;
+                        if state & distinguished_mask == 0 {
+                            break true;
+                        }
+                        if len >= max_trail_len {
+                            // Most likely stuck in a cycle
+                            break false;
+                        }
+                        if len % 1024 == 0 && is_cancelled() {
+                            return Ok(());
+                        }
+                    };
+                    if steps.fetch_add(len, Ordering::Relaxed) + len >= max_steps {
+                        search_end.store(true, Ordering::Relaxed);
+                        return Ok(());
+                    }
+                    if !distinguished {
+                        continue 'trail;
+                    }
+
+                    let other = points.lock().unwrap().insert(state, (start, len));
+                    if let Some(other) = other {
+                        if let Some(collision) = walker.locate((start, len), other)? {
+                            *result.lock().unwrap() = Some(collision);
+                            search_end.store(true, Ordering::Relaxed);
+                            return Ok(());
+                        }
+                    }
+                }
+                Ok(())
+            };
+            workers.push(s.spawn(move || {
+                // Stop the other workers instead of leaving them to walk forever
+                let outcome = match panic::catch_unwind(AssertUnwindSafe(walk)) {
+                    Ok(outcome) => outcome.map_err(PowError::from),
+                    Err(payload) => Err(PowError::from_panic(payload)),
+                };
+                if outcome.is_err() {
+                    search_end.store(true, Ordering::Relaxed);
+                }
+                outcome
+            }));
+        }
+        let mut outcome = Ok(());
+        for worker in workers {
+            let joined = worker
+                .join()
+                .unwrap_or_else(|p| Err(PowError::from_panic(p)));
+            outcome = outcome.and(joined);
+        }
+        outcome
+    })?;
+
+    match result.into_inner().unwrap() {
+        Some(collision) => Ok(Some(collision)),
+        None if thread_config
+            .cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.is_cancelled()) =>
+        {
+            Err(PowError::Cancelled)
+        }
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{search_collision, Collision, CollisionParameters};
+    use crate::{Fnv1a64, Hash, PowError, Sha256, ThreadConfig};
+
+    #[test]
+    fn test_sha256_collision() {
+        let mut hash_mask = [0u8; 32];
+        hash_mask[..4].fill(0xff);
+        let params = CollisionParameters::<Sha256> {
+            hash_mask,
+            plaintext_character_map: vec![(b'a'..=b'z').collect(); 8],
+            distinguished_bits: None,
+        };
+        let thread_config = ThreadConfig {
+            thread_count: Some(4),
+            cancel: None,
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+        match search_collision(Sha256::default(), params, &thread_config) {
+            Ok(Some(Collision {
+                first: a,
+                second: b,
+            })) => {
+                assert_ne!(a, b);
+                assert_eq!(
+                    Sha256::default().hash(&a)[..4],
+                    Sha256::default().hash(&b)[..4]
+                );
+            }
+            _ => panic!("Unexpected result"),
+        }
+    }
+
+    #[test]
+    fn test_scattered_mask_collision() {
+        // 24 bits spread over the digest, with a fixed prefix and suffix
+        let hash_mask = 0x0f00_f0f0_0ff0_00f0u64.to_be_bytes();
+        let mut plaintext_character_map: Vec<Vec<u8>> = b"id=".iter().map(|&c| vec![c]).collect();
+        plaintext_character_map.extend(vec![b"0123456789abcdef".to_vec(); 8]);
+        plaintext_character_map.push(b";".to_vec());
+        let params = CollisionParameters::<Fnv1a64> {
+            hash_mask,
+            plaintext_character_map,
+            distinguished_bits: Some(4),
+        };
+        let thread_config = ThreadConfig {
+            thread_count: Some(2),
+            cancel: None,
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+        match search_collision(Fnv1a64::default(), params, &thread_config) {
+            Ok(Some(Collision {
+                first: a,
+                second: b,
+            })) => {
+                assert_ne!(a, b);
+                let (ha, hb) = (Fnv1a64::default().hash(&a), Fnv1a64::default().hash(&b));
+                for i in 0..8 {
+                    assert_eq!(ha[i] & hash_mask[i], hb[i] & hash_mask[i]);
+                }
+            }
+            _ => panic!("Unexpected result"),
+        }
+        let params = CollisionParameters::<Fnv1a64> {
+            hash_mask: [0; 8],
+            plaintext_character_map: vec![b"ab".to_vec()],
+            distinguished_bits: None,
+        };
+        assert_eq!(
+            search_collision(Fnv1a64::default(), params, &thread_config),
+            Err(PowError::CollisionMaskBits { bits: 0 })
+        );
+        let params = CollisionParameters::<Fnv1a64> {
+            hash_mask: [0xff; 8],
+            plaintext_character_map: vec![b"a".to_vec(); 4],
+            distinguished_bits: None,
+        };
+        assert_eq!(
+            search_collision(Fnv1a64::default(), params, &thread_config),
+            Err(PowError::CollisionKeyspace)
+        );
+    }
+
+    #[test]
+    fn test_collision_not_found() {
+        // Two plaintexts cannot agree on 16 bits of their digests that differ
+        let (a, b) = (Fnv1a64::default().hash(b"a"), Fnv1a64::default().hash(b"b"));
+        let mut hash_mask = [0; 8];
+        let i = (0..8).find(|&i| a[i] != b[i]).unwrap();
+        hash_mask[i] = 0xff;
+        hash_mask[(i + 1) % 8] = 0xff;
+        let params = CollisionParameters::<Fnv1a64> {
+            hash_mask,
+            plaintext_character_map: vec![b"ab".to_vec()],
+            distinguished_bits: None,
+        };
+        let thread_config = ThreadConfig {
+            thread_count: Some(0),
+            cancel: None,
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+        assert_eq!(
+            search_collision(Fnv1a64::default(), params, &thread_config),
+            Ok(None)
+        );
+    }
+}
@@ -0,0 +1,59 @@
+const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
+pub(crate) const BASE64_CHARS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Appends the lowercase hex encoding of `bytes` to `out`.
+pub(crate) fn hex_encode(bytes: &[u8], out: &mut Vec<u8>) {
+    out.reserve(bytes.len() * 2);
+    for &b in bytes {
+        out.push(HEX_CHARS[(b >> 4) as usize]);
+        out.push(HEX_CHARS[(b & 0xf) as usize]);
+    }
+}
+
+/// Appends the padded standard base64 encoding of `bytes` to `out`.
+pub(crate) fn base64_encode(bytes: &[u8], out: &mut Vec<u8>) {
+    out.reserve(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        out.push(BASE64_CHARS[(n >> 18) as usize & 0x3f]);
+        out.push(BASE64_CHARS[(n >> 12) as usize & 0x3f]);
+        if chunk.len() > 1 {
+            out.push(BASE64_CHARS[(n >> 6) as usize & 0x3f]);
+        } else {
+            out.push(b'=');
+        }
+        if chunk.len() > 2 {
+            out.push(BASE64_CHARS[n as usize & 0x3f]);
+        } else {
+            out.push(b'=');
+        }
+    }
+}
+
+/// Decodes padded standard base64. Returns `None` for malformed input.
+pub(crate) fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let s = s.as_bytes();
+    if !s.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(s.len() / 4 * 3);
+    for (i, chunk) in s.chunks(4).enumerate() {
+        let last = i == s.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return None;
+        }
+        let mut n = 0u32;
+        for &c in &chunk[..4 - padding] {
+            let v = BASE64_CHARS.iter().position(|&a| a == c)? as u32;
+            n = n << 6 | v;
+        }
+        n <<= 6 * padding as u32;
+        let bytes = n.to_be_bytes();
+        out.extend_from_slice(&bytes[1..4 - padding]);
+    }
+    Some(out)
+}
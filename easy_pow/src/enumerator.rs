@@ -0,0 +1,208 @@
+use crate::Alphabet;
+
+/// Walks the plaintexts of a `plaintext_character_map` in the order searches
+/// enumerate them, like an odometer whose last position turns fastest.
+///
+/// Moving to the next plaintext only rewrites the positions that changed, so
+/// long plaintexts with mostly fixed positions cost no more per step than
+/// short ones. With [`Tokens`](crate::Tokens) of varying lengths the
+/// positions after a change move as well, and are rewritten from there on.
+/// The [`Iterator`] implementation copies each plaintext; use
+/// [`plaintext`](Self::plaintext) and [`advance`](Self::advance) to walk
+/// without allocating.
+#[derive(Debug, Clone)]
+pub struct Enumerator<'a, A = Vec<u8>> {
+    plaintext_character_map: &'a [A],
+    indices: Vec<usize>,
+    plaintext: Vec<u8>,
+    /// `ends[pos]` is where the token of `pos` ends in `plaintext`. Unused
+    /// for single-byte alphabets, where it is `pos + 1`.
+    ends: Vec<usize>,
+    /// Whether `plaintext` is a candidate, which stops being true after the
+    /// last one or right away if an alphabet is empty.
+    valid: bool,
+}
+
+impl<'a, A: Alphabet> Enumerator<'a, A> {
+    /// Starts at the first plaintext.
+    pub fn new(plaintext_character_map: &'a [A]) -> Self {
+        let valid = plaintext_character_map
+            .iter()
+            .all(|chars| !chars.is_empty());
+        let mut enumerator = Self {
+            plaintext_character_map,
+            indices: vec![0; plaintext_character_map.len()],
+            plaintext: Vec::with_capacity(plaintext_character_map.len()),
+            ends: Vec::new(),
+            valid,
+        };
+        if A::SINGLE_BYTE {
+            enumerator.plaintext = plaintext_character_map
+                .iter()
+                .map(|chars| if chars.is_empty() { 0 } else { chars.byte(0) })
+                .collect();
+        } else if valid {
+            enumerator.ends = vec![0; plaintext_character_map.len()];
+            enumerator.rewrite_from(0);
+        }
+        enumerator
+    }
+
+    /// Writes the tokens of the positions from `pos` on.
+    fn rewrite_from(&mut self, pos: usize) {
+        let start = pos.checked_sub(1).map_or(0, |p| self.ends[p]);
+        self.plaintext.truncate(start);
+        for p in pos..self.indices.len() {
+            self.plaintext_character_map[p].push_token(self.indices[p], &mut self.plaintext);
+            self.ends[p] = self.plaintext.len();
+        }
+    }
+
+    /// Moves to the plaintext at `index` in enumeration order, or past the
+    /// last one if there are not that many.
+    pub fn seek(&mut self, mut index: u128) {
+        if self.plaintext_character_map.iter().any(|c| c.is_empty()) {
+            return;
+        }
+        for pos in (0..self.indices.len()).rev() {
+            let chars = &self.plaintext_character_map[pos];
+            let digit = (index % chars.len() as u128) as usize;
+            index /= chars.len() as u128;
+            self.indices[pos] = digit;
+            if A::SINGLE_BYTE {
+                self.plaintext[pos] = chars.byte(digit);
+            }
+        }
+        if !A::SINGLE_BYTE {
+            self.rewrite_from(0);
+        }
+        self.valid = index == 0;
+    }
+
+    /// Moves to the plaintext with the given alphabet positions.
+    pub(crate) fn set_indices(&mut self, indices: &[usize]) {
+        self.indices.copy_from_slice(indices);
+        if A::SINGLE_BYTE {
+            for (pos, &digit) in indices.iter().enumerate() {
+                self.plaintext[pos] = self.plaintext_character_map[pos].byte(digit);
+            }
+        } else {
+            self.rewrite_from(0);
+        }
+        self.valid = true;
+    }
+
+    /// The current plaintext, or `None` once every plaintext was visited.
+    pub fn plaintext(&self) -> Option<&[u8]> {
+        self.valid.then_some(&self.plaintext[..])
+    }
+
+    /// The position of each token of the current plaintext in its alphabet.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Moves to the next plaintext. Returns the first position that changed,
+    /// or `None` if the current plaintext was the last one.
+    #[inline]
+    pub fn advance(&mut self) -> Option<usize> {
+        if !self.valid {
+            return None;
+        }
+        for pos in (0..self.indices.len()).rev() {
+            let chars = &self.plaintext_character_map[pos];
+            self.indices[pos] += 1;
+            if self.indices[pos] < chars.len() {
+                if A::SINGLE_BYTE {
+                    self.plaintext[pos] = chars.byte(self.indices[pos]);
+                } else {
+                    self.rewrite_from(pos);
+                }
+                return Some(pos);
+            }
+            self.indices[pos] = 0;
+            if A::SINGLE_BYTE {
+                self.plaintext[pos] = chars.byte(0);
+            }
+        }
+        self.valid = false;
+        if !A::SINGLE_BYTE {
+            self.rewrite_from(0);
+        }
+        None
+    }
+}
+
+impl<A: Alphabet> Iterator for Enumerator<'_, A> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        let plaintext = self.plaintext()?.to_vec();
+        self.advance();
+        Some(plaintext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Enumerator;
+    use crate::Tokens;
+
+    #[test]
+    fn test_enumerator() {
+        let map = vec![b"ab".to_vec(), b"xyz".to_vec()];
+        let all: Vec<Vec<u8>> = Enumerator::new(&map).collect();
+        assert_eq!(all, [&b"ax"[..], b"ay", b"az", b"bx", b"by", b"bz"]);
+
+        let mut enumerator = Enumerator::new(&map);
+        enumerator.seek(2);
+        assert_eq!(enumerator.plaintext(), Some(&b"az"[..]));
+        assert_eq!(enumerator.indices(), [0, 2]);
+        assert_eq!(enumerator.advance(), Some(0));
+        assert_eq!(enumerator.plaintext(), Some(&b"bx"[..]));
+        assert_eq!(enumerator.advance(), Some(1));
+        enumerator.seek(5);
+        assert_eq!(enumerator.advance(), None);
+        assert_eq!(enumerator.plaintext(), None);
+        enumerator.seek(6);
+        assert_eq!(enumerator.plaintext(), None);
+
+        // One empty plaintext, and none at all with an empty alphabet
+        assert_eq!(
+            Enumerator::<Vec<u8>>::new(&[]).collect::<Vec<_>>(),
+            [vec![]]
+        );
+        assert_eq!(Enumerator::new(&[b"a".to_vec(), vec![]]).count(), 0);
+
+        // A long fixed prefix only costs the positions that turn
+        let mut map = vec![vec![b'-']; 4096];
+        map.push(b"0123456789".to_vec());
+        let mut enumerator = Enumerator::new(&map);
+        assert_eq!(enumerator.advance(), Some(4096));
+        assert_eq!(enumerator.by_ref().count(), 9);
+
+        // Tokens of varying lengths, including an empty one
+        let map = vec![
+            Tokens::new(["root", "admin"]),
+            Tokens::new(["", "!", "ab"]),
+            Tokens::from_bytes("01"),
+        ];
+        let all: Vec<Vec<u8>> = Enumerator::new(&map).collect();
+        assert_eq!(all.len(), 12);
+        assert_eq!(all[0], b"root0");
+        assert_eq!(all[5], b"rootab1");
+        assert_eq!(all[6], b"admin0");
+        assert_eq!(all[11], b"adminab1");
+        let mut enumerator = Enumerator::new(&map);
+        enumerator.seek(9);
+        assert_eq!(enumerator.plaintext(), Some(&b"admin!1"[..]));
+        assert_eq!(enumerator.advance(), Some(1));
+        assert_eq!(enumerator.plaintext(), Some(&b"adminab0"[..]));
+        enumerator.seek(12);
+        assert_eq!(enumerator.plaintext(), None);
+        assert_eq!(
+            Enumerator::new(&[Tokens::new(["a"]), Tokens::default()]).count(),
+            0
+        );
+    }
+}
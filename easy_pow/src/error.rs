@@ -0,0 +1,57 @@
+use std::any::Any;
+
+use thiserror::Error;
+
+/// Why a search could not be carried out.
+///
+/// Not finding a solution is not an error; searches report
+/// [`SearchOutcome::NotFound`](crate::SearchOutcome::NotFound) then.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum PowError {
+    #[error("the alphabet at position {position} of plaintext_character_map is empty")]
+    EmptyAlphabet { position: usize },
+    #[error("target_hash has {actual} bytes, but the hash output has {expected}")]
+    TargetHashLength { expected: usize, actual: usize },
+    #[error("target_hash_mask has {actual} bytes, but the hash output has {expected}")]
+    TargetHashMaskLength { expected: usize, actual: usize },
+    #[error("hash_mask selects {bits} bits, but collision search needs 1 to 128")]
+    CollisionMaskBits { bits: usize },
+    #[error("plaintext_character_map holds fewer than two plaintexts, so no collision exists")]
+    CollisionKeyspace,
+    #[error("the search was cancelled")]
+    Cancelled,
+    #[error("the hash function failed: {0}")]
+    Hash(#[from] HashError),
+    #[error("a search worker panicked: {message}")]
+    WorkerPanic { message: String },
+    #[error("a search thread channel was closed unexpectedly")]
+    ChannelClosed,
+}
+
+/// A failure reported by [`Hash::try_hash`](crate::Hash::try_hash).
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("{message}")]
+pub struct HashError {
+    pub message: String,
+}
+
+impl HashError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl PowError {
+    pub(crate) fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "unknown panic payload".to_string()
+        };
+        PowError::WorkerPanic { message }
+    }
+}
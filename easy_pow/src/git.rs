@@ -0,0 +1,353 @@
+//! Vanity object IDs for git commits and tags.
+//!
+//! An object ID is the hash of `<type> <length>\0<body>`. A nonce of fixed
+//! length is inserted either as an extra header line or as a trailer at the
+//! end of the message, so the length in the object header is known before
+//! the search starts. Everything before the nonce is absorbed into a hash
+//! midstate once.
+
+use sha2::Digest;
+use thiserror::Error;
+
+use crate::{
+    search_with_hasher, Charset, Hash, HashOutput, PlaintextTemplate, PowError,
+    PowSearchParameters, ThreadConfig,
+};
+
+/// The name of the nonce header line.
+pub const NONCE_HEADER: &str = "nonce";
+/// The key of the nonce trailer line.
+pub const NONCE_TRAILER: &str = "Nonce";
+
+const BLOCK_SIZE: usize = 64;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum VanityError {
+    #[error("prefix is not a hex string")]
+    InvalidPrefix,
+    #[error("prefix is longer than the object ID")]
+    PrefixTooLong,
+    #[error("object has no blank line between headers and message")]
+    MalformedObject,
+    #[error("no nonce found")]
+    NotFound,
+    #[error(transparent)]
+    Search(#[from] PowError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Commit,
+    Tag,
+}
+
+impl ObjectKind {
+    fn name(self) -> &'static str {
+        match self {
+            ObjectKind::Commit => "commit",
+            ObjectKind::Tag => "tag",
+        }
+    }
+}
+
+/// The hash function of the repository (`extensions.objectFormat`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectFormat {
+    Sha1,
+    Sha256,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoncePlacement {
+    /// A `nonce <value>` line after the other headers. The message is left
+    /// untouched, but git only shows the line with `git cat-file`.
+    Header,
+    /// A `Nonce: <value>` trailer at the end of the message. Nothing follows
+    /// the nonce, so this hashes the fewest bytes per candidate.
+    Trailer,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VanityParameters {
+    pub kind: ObjectKind,
+    pub format: ObjectFormat,
+    pub placement: NoncePlacement,
+    /// The wanted start of the hex object ID. May have an odd length.
+    pub prefix: String,
+}
+
+/// Hashes `midstate || bytes || suffix`.
+#[derive(Clone)]
+struct MidstateHash<D> {
+    midstate: D,
+    suffix: Vec<u8>,
+}
+
+macro_rules! midstate_hash_impl {
+    ($digest:ty, $len:expr) => {
+        impl Hash for MidstateHash<$digest> {
+            type Output = [u8; $len];
+            const PARALLEL_BLOCK_SIZE: usize = 4096;
+
+            fn hash(&self, bytes: &[u8]) -> [u8; $len] {
+                let mut hasher = self.midstate.clone();
+                hasher.update(bytes);
+                hasher.update(&self.suffix);
+                hasher.finalize().into()
+            }
+        }
+    };
+}
+
+midstate_hash_impl!(sha1::Sha1, 20);
+midstate_hash_impl!(sha2::Sha256, 32);
+
+/// The ID of a raw object body in the given format.
+pub fn object_id(kind: ObjectKind, format: ObjectFormat, body: &[u8]) -> Vec<u8> {
+    let header = format!("{} {}\0", kind.name(), body.len());
+    match format {
+        ObjectFormat::Sha1 => sha1::Sha1::new()
+            .chain_update(header)
+            .chain_update(body)
+            .finalize()
+            .to_vec(),
+        ObjectFormat::Sha256 => sha2::Sha256::new()
+            .chain_update(header)
+            .chain_update(body)
+            .finalize()
+            .to_vec(),
+    }
+}
+
+fn parse_prefix(prefix: &str, hash_len: usize) -> Result<(Vec<u8>, Vec<u8>), VanityError> {
+    if prefix.len() > hash_len * 2 {
+        return Err(VanityError::PrefixTooLong);
+    }
+    let mut target = vec![0u8; hash_len];
+    let mut mask = vec![0u8; hash_len];
+    for (i, c) in prefix.chars().enumerate() {
+        let nibble = c.to_digit(16).ok_or(VanityError::InvalidPrefix)? as u8;
+        let shift = if i % 2 == 0 { 4 } else { 0 };
+        target[i / 2] |= nibble << shift;
+        mask[i / 2] |= 0xf << shift;
+    }
+    Ok((target, mask))
+}
+
+/// Splits `body` around the place where the nonce goes.
+fn split_body(body: &[u8], placement: NoncePlacement) -> Result<(Vec<u8>, Vec<u8>), VanityError> {
+    match placement {
+        NoncePlacement::Header => {
+            let header_end = body
+                .windows(2)
+                .position(|w| w == b"\n\n")
+                .ok_or(VanityError::MalformedObject)?
+                + 1;
+            // Drop the nonce of an earlier run
+            let mut prefix = Vec::new();
+            for line in body[..header_end].split_inclusive(|&c| c == b'\n') {
+                if !line.starts_with(format!("{} ", NONCE_HEADER).as_bytes()) {
+                    prefix.extend_from_slice(line);
+                }
+            }
+            prefix.extend_from_slice(format!("{} ", NONCE_HEADER).as_bytes());
+            let mut suffix = b"\n".to_vec();
+            suffix.extend_from_slice(&body[header_end..]);
+            Ok((prefix, suffix))
+        }
+        NoncePlacement::Trailer => {
+            let mut prefix = body.to_vec();
+            if !prefix.ends_with(b"\n") {
+                prefix.push(b'\n');
+            }
+            // Drop the nonce of an earlier run, and the paragraph break it
+            // started, if any
+            let last_line_start = prefix[..prefix.len() - 1]
+                .iter()
+                .rposition(|&c| c == b'\n')
+                .map_or(0, |i| i + 1);
+            if last_line_start > 0
+                && prefix[last_line_start..].starts_with(format!("{}: ", NONCE_TRAILER).as_bytes())
+            {
+                prefix.truncate(last_line_start);
+                if prefix.ends_with(b"\n\n") {
+                    prefix.pop();
+                }
+            }
+            // Join an existing trailer block instead of starting a new paragraph
+            let last_line = prefix[..prefix.len() - 1]
+                .rsplit(|&c| c == b'\n')
+                .next()
+                .unwrap_or_default();
+            let is_trailer = last_line.iter().position(|&c| c == b':').is_some_and(|i| {
+                i > 0
+                    && last_line[..i]
+                        .iter()
+                        .all(|&c| c.is_ascii_alphanumeric() || c == b'-')
+            });
+            if !is_trailer {
+                prefix.push(b'\n');
+            }
+            prefix.extend_from_slice(format!("{}: ", NONCE_TRAILER).as_bytes());
+            Ok((prefix, b"\n".to_vec()))
+        }
+    }
+}
+
+fn search_nonce<D>(
+    kind: ObjectKind,
+    body_prefix: &[u8],
+    body_suffix: &[u8],
+    target: &[u8],
+    mask: &[u8],
+    thread_config: &ThreadConfig,
+) -> Result<Vec<u8>, VanityError>
+where
+    D: Digest + Clone,
+    MidstateHash<D>: Hash,
+{
+    let bits = mask.iter().map(|m| m.count_ones()).sum::<u32>() as usize;
+    // 62 characters give a bit less than 6 bits each
+    let min_length = (bits * 100).div_ceil(595).max(1);
+    for length in min_length..min_length + 3 {
+        let body_len = body_prefix.len() + length + body_suffix.len();
+        let mut prefix = format!("{} {}\0", kind.name(), body_len).into_bytes();
+        prefix.extend_from_slice(body_prefix);
+        let absorbed = prefix.len() / BLOCK_SIZE * BLOCK_SIZE;
+        let hasher = MidstateHash {
+            midstate: D::new().chain_update(&prefix[..absorbed]),
+            suffix: body_suffix.to_vec(),
+        };
+        let plaintext_character_map = PlaintextTemplate::new()
+            .fixed(&prefix[absorbed..])
+            .charset(Charset::Alnum, length)
+            .build();
+        let params = PowSearchParameters::<MidstateHash<D>> {
+            target_hash: HashOutput::from_slice(target).unwrap(),
+            target_hash_mask: HashOutput::from_slice(mask).unwrap(),
+            plaintext_character_map,
+        };
+        if let Some(solution) =
+            search_with_hasher(hasher, params, thread_config)?.into_solution()?
+        {
+            let nonce = &solution.plaintext[solution.plaintext.len() - length..];
+            let mut body = body_prefix.to_vec();
+            body.extend_from_slice(nonce);
+            body.extend_from_slice(body_suffix);
+            return Ok(body);
+        }
+    }
+    Err(VanityError::NotFound)
+}
+
+/// Inserts a nonce into the raw object `body` (as printed by `git cat-file`)
+/// so that its ID starts with `params.prefix`, and returns the new body.
+///
+/// Signatures on the object do not cover the nonce and become invalid.
+pub fn search_vanity(
+    body: &[u8],
+    params: &VanityParameters,
+    thread_config: &ThreadConfig,
+) -> Result<Vec<u8>, VanityError> {
+    let hash_len = match params.format {
+        ObjectFormat::Sha1 => 20,
+        ObjectFormat::Sha256 => 32,
+    };
+    let (target, mask) = parse_prefix(&params.prefix, hash_len)?;
+    let (body_prefix, body_suffix) = split_body(body, params.placement)?;
+    match params.format {
+        ObjectFormat::Sha1 => search_nonce::<sha1::Sha1>(
+            params.kind,
+            &body_prefix,
+            &body_suffix,
+            &target,
+            &mask,
+            thread_config,
+        ),
+        ObjectFormat::Sha256 => search_nonce::<sha2::Sha256>(
+            params.kind,
+            &body_prefix,
+            &body_suffix,
+            &target,
+            &mask,
+            thread_config,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        object_id, search_vanity, NoncePlacement, ObjectFormat, ObjectKind, VanityError,
+        VanityParameters,
+    };
+    use crate::ThreadConfig;
+
+    const COMMIT: &[u8] = b"tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n\
+author A U Thor <author@example.com> 1112911993 -0700\n\
+committer C O Mitter <committer@example.com> 1112911993 -0700\n\
+\n\
+Initial commit\n";
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_object_id() {
+        // `git hash-object -t commit --stdin`
+        assert_eq!(
+            hex(&object_id(ObjectKind::Commit, ObjectFormat::Sha1, COMMIT)),
+            "19beeadb53c246941af6b333137f5e23940ed533"
+        );
+    }
+
+    #[test]
+    fn test_search_vanity() {
+        let thread_config = ThreadConfig {
+            thread_count: Some(2),
+            cancel: None,
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+        let mut params = VanityParameters {
+            kind: ObjectKind::Commit,
+            format: ObjectFormat::Sha1,
+            placement: NoncePlacement::Header,
+            prefix: "c0f".to_string(),
+        };
+        let body = search_vanity(COMMIT, &params, &thread_config).unwrap();
+        assert!(hex(&object_id(params.kind, params.format, &body)).starts_with("c0f"));
+        let text = String::from_utf8(body.clone()).unwrap();
+        assert!(text.contains(" -0700\nnonce "));
+        assert!(text.ends_with("\n\nInitial commit\n"));
+
+        // Running again replaces the nonce
+        params.prefix = "beef".to_string();
+        let body = search_vanity(&body, &params, &thread_config).unwrap();
+        assert!(hex(&object_id(params.kind, params.format, &body)).starts_with("beef"));
+        assert_eq!(String::from_utf8(body).unwrap().matches("nonce").count(), 1);
+
+        params.format = ObjectFormat::Sha256;
+        params.placement = NoncePlacement::Trailer;
+        let body = search_vanity(COMMIT, &params, &thread_config).unwrap();
+        assert!(hex(&object_id(params.kind, params.format, &body)).starts_with("beef"));
+        let text = String::from_utf8(body.clone()).unwrap();
+        assert!(text.contains("\n\nInitial commit\n\nNonce: "));
+
+        // Running again replaces the trailer
+        params.prefix = "c0f".to_string();
+        let body = search_vanity(&body, &params, &thread_config).unwrap();
+        assert!(hex(&object_id(params.kind, params.format, &body)).starts_with("c0f"));
+        let text = String::from_utf8(body).unwrap();
+        assert_eq!(text.matches("Nonce:").count(), 1);
+        assert!(text.contains("\n\nInitial commit\n\nNonce: "));
+
+        params.prefix = "xyz".to_string();
+        assert_eq!(
+            search_vanity(COMMIT, &params, &thread_config),
+            Err(VanityError::InvalidPrefix)
+        );
+    }
+}
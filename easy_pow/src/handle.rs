@@ -0,0 +1,485 @@
+#[cfg(feature = "async")]
+use std::{
+    sync::{Arc, Mutex, OnceLock},
+    task::Waker,
+};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::channel::{Receiver, RecvTimeoutError, Sender};
+
+use crate::{
+    search_by_hash_type, search_with_hasher, Alphabet, CancellationToken, Hash, HashType, PowError,
+    PowSearchParameters, SearchProgress, SearchReport, ThreadConfig,
+};
+#[cfg(feature = "async")]
+use crate::{Runner, Solver};
+
+/// A search running in the background.
+///
+/// Dropping the handle cancels the search without waiting for it. With the
+/// `async` feature the handle is also a [`Future`](std::future::Future) of
+/// the result.
+#[derive(Debug)]
+pub struct SearchHandle<O> {
+    receiver: Receiver<Result<SearchReport<O>, PowError>>,
+    /// The thread running the search, unless it runs on a
+    /// [`Solver`](crate::Solver).
+    thread: Option<thread::JoinHandle<()>>,
+    result: Option<Result<SearchReport<O>, PowError>>,
+    cancel: CancellationToken,
+    progress: SearchProgress,
+    start: Instant,
+    /// Woken once the result has been sent.
+    #[cfg(feature = "async")]
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+/// Passes the result of a search to its [`SearchHandle`].
+///
+/// Dropping it without sending, as when the search is lost, closes the
+/// channel, which the handle reports as [`PowError::ChannelClosed`].
+struct Notifier<O> {
+    sender: Option<Sender<Result<SearchReport<O>, PowError>>>,
+    #[cfg(feature = "async")]
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl<O> Notifier<O> {
+    fn send(mut self, result: Result<SearchReport<O>, PowError>) {
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send(result);
+        }
+    }
+}
+
+impl<O> Drop for Notifier<O> {
+    fn drop(&mut self) {
+        // Close the channel before waking, so the woken task cannot miss both
+        // the result and the disconnection
+        drop(self.sender.take());
+        #[cfg(feature = "async")]
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<O: Send + 'static> SearchHandle<O> {
+    /// Starts a search with a copy of `thread_config` whose token is a child
+    /// of the caller's and whose progress is tracked. `start` returns the
+    /// thread it started the search on, if any, and the search reports to
+    /// the notifier.
+    fn new(
+        mut thread_config: ThreadConfig,
+        start: impl FnOnce(ThreadConfig, Notifier<O>) -> Option<thread::JoinHandle<()>>,
+    ) -> Self {
+        let cancel = match &thread_config.cancel {
+            Some(cancel) => cancel.child(),
+            None => CancellationToken::new(),
+        };
+        let progress = thread_config.progress.clone().unwrap_or_default();
+        thread_config.cancel = Some(cancel.clone());
+        thread_config.progress = Some(progress.clone());
+
+        let (sender, receiver) = crossbeam::channel::bounded(1);
+        #[cfg(feature = "async")]
+        let waker = Arc::new(Mutex::new(None::<Waker>));
+        let notifier = Notifier {
+            sender: Some(sender),
+            #[cfg(feature = "async")]
+            waker: waker.clone(),
+        };
+        Self {
+            receiver,
+            thread: start(thread_config, notifier),
+            result: None,
+            cancel,
+            progress,
+            start: Instant::now(),
+            #[cfg(feature = "async")]
+            waker,
+        }
+    }
+
+    /// Runs `search` on a new thread.
+    fn spawn(
+        thread_config: ThreadConfig,
+        search: impl FnOnce(&ThreadConfig) -> Result<SearchReport<O>, PowError> + Send + 'static,
+    ) -> Self {
+        Self::new(thread_config, |thread_config, notifier| {
+            Some(thread::spawn(move || notifier.send(search(&thread_config))))
+        })
+    }
+}
+
+impl<O> SearchHandle<O> {
+    /// Waits up to `timeout` for the result, or for as long as it takes.
+    fn wait(&mut self, timeout: Option<Duration>) -> Option<&Result<SearchReport<O>, PowError>> {
+        if self.result.is_none() {
+            let received = match timeout {
+                Some(timeout) => self.receiver.recv_timeout(timeout),
+                None => self
+                    .receiver
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            self.result = match received {
+                Ok(result) => Some(result),
+                Err(RecvTimeoutError::Timeout) => None,
+                // The search panicked or was lost before sending a result
+                Err(RecvTimeoutError::Disconnected) => Some(Err(match self.thread.take() {
+                    Some(thread) => match thread.join() {
+                        Ok(()) => PowError::ChannelClosed,
+                        Err(payload) => PowError::from_panic(payload),
+                    },
+                    None => PowError::ChannelClosed,
+                })),
+            };
+        }
+        self.result.as_ref()
+    }
+
+    /// The result if the search has finished, without blocking.
+    pub fn try_result(&mut self) -> Option<&Result<SearchReport<O>, PowError>> {
+        self.wait(Some(Duration::ZERO))
+    }
+
+    /// Waits up to `timeout` for the search to finish.
+    pub fn join_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Option<&Result<SearchReport<O>, PowError>> {
+        self.wait(Some(timeout))
+    }
+
+    /// Waits for the search to finish.
+    pub fn join(mut self) -> Result<SearchReport<O>, PowError> {
+        self.wait(None);
+        self.result
+            .take()
+            .expect("wait without timeout returns a result")
+    }
+
+    /// Stops the search, which then reports
+    /// [`SearchOutcome::Cancelled`](crate::SearchOutcome::Cancelled) unless
+    /// it has already finished.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub fn is_finished(&mut self) -> bool {
+        self.try_result().is_some()
+    }
+
+    /// The hashes computed so far.
+    pub fn hashes(&self) -> u64 {
+        self.progress.hashes()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Hashes per second since the search started.
+    pub fn hash_rate(&self) -> f64 {
+        let seconds = self.elapsed().as_secs_f64();
+        if seconds > 0.0 {
+            self.hashes() as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+impl<O> Drop for SearchHandle<O> {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+// The result is never pinned in place
+impl<O> Unpin for SearchHandle<O> {}
+
+#[cfg(feature = "async")]
+impl<O> std::future::Future for SearchHandle<O> {
+    type Output = Result<SearchReport<O>, PowError>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let this = self.get_mut();
+        // Register before checking, so a result sent in between still wakes us
+        *this.waker.lock().unwrap() = Some(cx.waker().clone());
+        match this.try_result() {
+            Some(_) => std::task::Poll::Ready(this.result.take().expect("result is ready")),
+            None => std::task::Poll::Pending,
+        }
+    }
+}
+
+/// Same as [`search`](crate::search), but runs on a background thread.
+pub fn spawn_search<H: Hash + Default + 'static>(
+    params: PowSearchParameters<H, impl Alphabet + 'static>,
+    thread_config: ThreadConfig,
+) -> SearchHandle<H::Output> {
+    spawn_search_with_hasher(H::default(), params, thread_config)
+}
+
+/// Same as [`search_with_hasher`], but runs on a background thread.
+pub fn spawn_search_with_hasher<H: Hash + 'static>(
+    hasher: H,
+    params: PowSearchParameters<H, impl Alphabet + 'static>,
+    thread_config: ThreadConfig,
+) -> SearchHandle<H::Output> {
+    SearchHandle::spawn(thread_config, move |thread_config| {
+        search_with_hasher(hasher, params, thread_config)
+    })
+}
+
+/// Same as [`search_by_hash_type`], but runs on a background thread.
+pub fn spawn_search_by_hash_type(
+    hash_type: HashType,
+    target_hash: Vec<u8>,
+    target_hash_mask: Vec<u8>,
+    plaintext_character_map: Vec<impl Alphabet + Clone + 'static>,
+    thread_config: ThreadConfig,
+) -> SearchHandle<Vec<u8>> {
+    SearchHandle::spawn(thread_config, move |thread_config| {
+        search_by_hash_type(
+            hash_type,
+            &target_hash,
+            &target_hash_mask,
+            &plaintext_character_map,
+            thread_config,
+        )
+    })
+}
+
+/// The pool that async searches run on, with one thread per CPU. The first
+/// async search starts it.
+#[cfg(feature = "async")]
+fn async_solver() -> &'static Solver {
+    static SOLVER: OnceLock<Solver> = OnceLock::new();
+    SOLVER.get_or_init(Solver::default)
+}
+
+/// Same as [`search`](crate::search), but resolves as a future. The search
+/// runs on a pool of threads shared by all async searches, which completes
+/// the future, so any executor can drive it and a search in flight holds no
+/// thread of its own. Dropping the future cancels the search.
+#[cfg(feature = "async")]
+pub async fn search_async<H: Hash + Default + 'static>(
+    params: PowSearchParameters<H, impl Alphabet + 'static>,
+    thread_config: ThreadConfig,
+) -> Result<SearchReport<H::Output>, PowError> {
+    search_with_hasher_async(H::default(), params, thread_config).await
+}
+
+/// Same as [`search_with_hasher`], but resolves as a future. See
+/// [`search_async`].
+#[cfg(feature = "async")]
+pub async fn search_with_hasher_async<H: Hash + 'static>(
+    hasher: H,
+    params: PowSearchParameters<H, impl Alphabet + 'static>,
+    thread_config: ThreadConfig,
+) -> Result<SearchReport<H::Output>, PowError> {
+    SearchHandle::new(thread_config, |thread_config, notifier| {
+        async_solver().start(hasher, params, &thread_config, |result| {
+            notifier.send(result)
+        });
+        None
+    })
+    .await
+}
+
+/// Same as [`search_by_hash_type`], but resolves as a future. See
+/// [`search_async`].
+#[cfg(feature = "async")]
+pub async fn search_by_hash_type_async(
+    hash_type: HashType,
+    target_hash: Vec<u8>,
+    target_hash_mask: Vec<u8>,
+    plaintext_character_map: Vec<impl Alphabet + Clone + 'static>,
+    thread_config: ThreadConfig,
+) -> Result<SearchReport<Vec<u8>>, PowError> {
+    SearchHandle::new(thread_config, |thread_config, notifier| {
+        async_solver().start_search_by_hash_type(
+            hash_type,
+            target_hash,
+            target_hash_mask,
+            plaintext_character_map,
+            thread_config,
+            |result| notifier.send(result),
+        );
+        None
+    })
+    .await
+}
+
+#[cfg(all(
+    test,
+    any(feature = "backend-openssl", feature = "backend-rust-crypto")
+))]
+mod tests {
+    use std::time::Duration;
+
+    use super::spawn_search_by_hash_type;
+    use crate::{CancellationToken, HashType, SearchOutcome, ThreadConfig};
+
+    #[test]
+    fn test_search_handle() {
+        let thread_config = ThreadConfig {
+            thread_count: Some(2),
+            cancel: None,
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+        let handle = spawn_search_by_hash_type(
+            HashType::Md5,
+            vec![0; 16],
+            vec![0; 16],
+            vec![b"ab".to_vec()],
+            thread_config.clone(),
+        );
+        let report = handle.join().unwrap();
+        assert!(matches!(report.outcome, SearchOutcome::Found(_)));
+
+        // Nothing matches a full 128-bit target within 2^64 candidates
+        let parent = CancellationToken::new();
+        let mut handle = spawn_search_by_hash_type(
+            HashType::Md5,
+            vec![0; 16],
+            vec![0xff; 16],
+            vec![(0..=255).collect::<Vec<u8>>(); 8],
+            ThreadConfig {
+                cancel: Some(parent.clone()),
+                ..thread_config
+            },
+        );
+        assert!(handle.try_result().is_none());
+        assert!(handle.join_timeout(Duration::from_millis(50)).is_none());
+        while handle.hashes() == 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        handle.cancel();
+        assert!(!parent.is_cancelled());
+        let report = handle.join().unwrap();
+        assert_eq!(report.outcome, SearchOutcome::Cancelled);
+        assert!(report.total_hashes > 0);
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_search_async() {
+        use std::future::Future;
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake};
+
+        use crate::{search_by_hash_type_async, SearchProgress};
+
+        struct ThreadWaker(std::thread::Thread);
+
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Arc::new(ThreadWaker(std::thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let thread_config = ThreadConfig {
+            thread_count: Some(2),
+            cancel: None,
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+
+        let mut future = Box::pin(search_by_hash_type_async(
+            HashType::Sha1,
+            vec![0; 20],
+            vec![0xf0; 1].into_iter().chain([0; 19]).collect(),
+            vec![(b'a'..=b'z').collect::<Vec<u8>>(); 4],
+            thread_config.clone(),
+        ));
+        let report = loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(report) => break report.unwrap(),
+                Poll::Pending => std::thread::park(),
+            }
+        };
+        assert_eq!(report.solution().unwrap().digest[0] & 0xf0, 0);
+
+        // Many searches in flight share the pool, and errors resolve too
+        let mut futures: Vec<_> = (0..32u8)
+            .map(|i| {
+                Box::pin(search_by_hash_type_async(
+                    HashType::Md5,
+                    vec![i; 16],
+                    vec![0xf0; 1].into_iter().chain([0; 15]).collect(),
+                    vec![(b'a'..=b'z').collect::<Vec<u8>>(); 4],
+                    thread_config.clone(),
+                ))
+            })
+            .collect();
+        let mut pending: Vec<usize> = (0..futures.len()).collect();
+        while !pending.is_empty() {
+            pending.retain(|&i| match futures[i].as_mut().poll(&mut cx) {
+                Poll::Ready(report) => {
+                    let digest = report.unwrap().solution().unwrap().digest.clone();
+                    assert_eq!(digest[0] & 0xf0, (i as u8) & 0xf0);
+                    false
+                }
+                Poll::Pending => true,
+            });
+            std::thread::park_timeout(Duration::from_millis(10));
+        }
+        let mut future = Box::pin(search_by_hash_type_async(
+            HashType::Md5,
+            vec![0; 4],
+            vec![0; 16],
+            vec![b"ab".to_vec()],
+            thread_config.clone(),
+        ));
+        let result = loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(result) => break result,
+                Poll::Pending => std::thread::park(),
+            }
+        };
+        assert_eq!(
+            result.unwrap_err(),
+            crate::PowError::TargetHashLength {
+                expected: 16,
+                actual: 4
+            }
+        );
+
+        // Dropping a pending future stops the search
+        let progress = SearchProgress::new();
+        let mut future = Box::pin(search_by_hash_type_async(
+            HashType::Md5,
+            vec![0; 16],
+            vec![0xff; 16],
+            vec![(0..=255).collect::<Vec<u8>>(); 8],
+            ThreadConfig {
+                progress: Some(progress.clone()),
+                ..thread_config
+            },
+        ));
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        while progress.hashes() == 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        drop(future);
+        std::thread::sleep(Duration::from_millis(100));
+        let hashes = progress.hashes();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(progress.hashes(), hashes);
+    }
+}
@@ -2,19 +2,28 @@ use std::str::FromStr;
 
 use thiserror::Error;
 
+use crate::{pipeline::Algorithm, ParsePipelineError, Pipeline};
+
 #[derive(Debug, Error)]
-#[error("invalid hash type")]
+#[error("invalid hash type{}", .reason.as_ref().map(|e| format!(": {}", e)).unwrap_or_default())]
 pub struct InvalidHashTypeError {
-    _priv: (),
+    #[source]
+    reason: Option<ParsePipelineError>,
 }
 
 impl InvalidHashTypeError {
     fn new() -> Self {
-        Self { _priv: () }
+        Self { reason: None }
+    }
+}
+
+impl From<ParsePipelineError> for InvalidHashTypeError {
+    fn from(e: ParsePipelineError) -> Self {
+        Self { reason: Some(e) }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum HashType {
     Md5,
     Sha1,
@@ -22,19 +31,30 @@ pub enum HashType {
     Sha256,
     Sha384,
     Sha512,
+    Crc32,
+    /// CRC-64/XZ
+    Crc64,
+    Adler32,
+    XxHash32,
+    XxHash64,
+    Fnv1a32,
+    Fnv1a64,
+    /// MurmurHash3 (x86, 32-bit)
+    Murmur3,
+    /// A composed hash such as `sha256(hex(md5(x)) || "salt")`. See [`Pipeline`].
+    Pipeline(Pipeline),
 }
 
 impl FromStr for HashType {
     type Err = InvalidHashTypeError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "md5" => Ok(Self::Md5),
-            "sha1" => Ok(Self::Sha1),
-            "sha224" => Ok(Self::Sha224),
-            "sha256" => Ok(Self::Sha256),
-            "sha384" => Ok(Self::Sha384),
-            "sha512" => Ok(Self::Sha512),
+        match Algorithm::from_name(&s.to_lowercase()) {
+            Some(algorithm) => Ok(algorithm.hash_type()),
+            _ if s.contains('(') => {
+                let pipeline = Pipeline::from_str(s)?;
+                Ok(pipeline.as_hash_type().unwrap_or(Self::Pipeline(pipeline)))
+            }
             _ => Err(InvalidHashTypeError::new()),
         }
     }
@@ -0,0 +1,355 @@
+//! Hashcash stamps (`1:bits:date:resource:ext:rand:counter`).
+//!
+//! A stamp is valid when the hash of the whole stamp string starts with
+//! `bits` zero bits. Classic hashcash uses SHA-1, but any [`Hash`] can be used
+//! for minting and verification.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+use crate::encoding::base64_encode;
+use crate::{
+    leading_bits_mask, search_with_hasher, Charset, Hash, HashOutput, PlaintextTemplate, PowError,
+    PowSearchParameters, ThreadConfig,
+};
+
+/// The mail header carrying a stamp.
+pub const HEADER: &str = "X-Hashcash";
+
+/// How far in the future a stamp date may be, to allow for clock skew.
+pub const FUTURE_GRACE: Duration = Duration::from_secs(2 * 24 * 60 * 60);
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ParseStampError {
+    #[error("unsupported stamp version")]
+    InvalidVersion,
+    #[error("invalid stamp format")]
+    InvalidFormat,
+    #[error("invalid bit count")]
+    InvalidBits,
+    #[error("not an {HEADER} header")]
+    InvalidHeader,
+}
+
+#[derive(Debug, Error)]
+pub enum MintError {
+    #[error("resource and extension must not contain ':'")]
+    InvalidField,
+    #[error("bit count exceeds the hash size")]
+    TooManyBits,
+    #[error("failed to generate the random field: {0}")]
+    Random(getrandom::Error),
+    #[error("no stamp found")]
+    NotFound,
+    #[error(transparent)]
+    Search(#[from] PowError),
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    #[error("stamp is for a different resource")]
+    ResourceMismatch,
+    #[error("stamp claims fewer bits than required")]
+    InsufficientBits,
+    #[error("hash does not have the claimed number of zero bits")]
+    InvalidHash,
+    #[error("invalid stamp date")]
+    InvalidDate,
+    #[error("stamp has expired")]
+    Expired,
+    #[error("stamp is dated in the future")]
+    FutureDate,
+}
+
+/// A version 1 hashcash stamp.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stamp {
+    pub bits: u32,
+    /// `YYMMDD[hhmm[ss]]` in UTC.
+    pub date: String,
+    pub resource: String,
+    pub extension: String,
+    pub rand: String,
+    pub counter: String,
+}
+
+impl Stamp {
+    /// Parses the value of an `X-Hashcash: <stamp>` header line.
+    pub fn from_header(line: &str) -> Result<Self, ParseStampError> {
+        let (name, value) = line.split_once(':').ok_or(ParseStampError::InvalidHeader)?;
+        if !name.trim().eq_ignore_ascii_case(HEADER) {
+            return Err(ParseStampError::InvalidHeader);
+        }
+        value.trim().parse()
+    }
+
+    pub fn to_header(&self) -> String {
+        format!("{}: {}", HEADER, self)
+    }
+
+    /// The stamp date as seconds since the Unix epoch.
+    pub fn timestamp(&self) -> Option<u64> {
+        parse_date(&self.date)
+    }
+
+    /// Whether the hash of the stamp starts with `self.bits` zero bits.
+    pub fn check<H: Hash + Default>(&self) -> bool {
+        let hash = H::default().hash(self.to_string().as_bytes());
+        leading_zero_bits(hash.as_slice()) >= self.bits as usize
+    }
+
+    /// Verifies the stamp for `resource`, requiring at least `min_bits` bits
+    /// and a date no older than `validity` before `now`.
+    pub fn verify<H: Hash + Default>(
+        &self,
+        resource: &str,
+        min_bits: u32,
+        now: SystemTime,
+        validity: Duration,
+    ) -> Result<(), VerifyError> {
+        if self.resource != resource {
+            return Err(VerifyError::ResourceMismatch);
+        }
+        if self.bits < min_bits {
+            return Err(VerifyError::InsufficientBits);
+        }
+        let date =
+            UNIX_EPOCH + Duration::from_secs(self.timestamp().ok_or(VerifyError::InvalidDate)?);
+        if date > now + FUTURE_GRACE {
+            return Err(VerifyError::FutureDate);
+        }
+        if now.duration_since(date).is_ok_and(|age| age > validity) {
+            return Err(VerifyError::Expired);
+        }
+        if !self.check::<H>() {
+            return Err(VerifyError::InvalidHash);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Stamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "1:{}:{}:{}:{}:{}:{}",
+            self.bits, self.date, self.resource, self.extension, self.rand, self.counter
+        )
+    }
+}
+
+impl FromStr for Stamp {
+    type Err = ParseStampError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split(':').collect();
+        if fields[0] != "1" {
+            return Err(ParseStampError::InvalidVersion);
+        }
+        let [_, bits, date, resource, extension, rand, counter] = fields[..] else {
+            return Err(ParseStampError::InvalidFormat);
+        };
+        Ok(Self {
+            bits: bits.parse().map_err(|_| ParseStampError::InvalidBits)?,
+            date: date.to_string(),
+            resource: resource.to_string(),
+            extension: extension.to_string(),
+            rand: rand.to_string(),
+            counter: counter.to_string(),
+        })
+    }
+}
+
+fn leading_zero_bits(hash: &[u8]) -> usize {
+    match hash.iter().position(|&b| b != 0) {
+        Some(i) => i * 8 + hash[i].leading_zeros() as usize,
+        None => hash.len() * 8,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+fn parse_date(date: &str) -> Option<u64> {
+    if !matches!(date.len(), 6 | 10 | 12) || !date.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let field = |i: usize| -> u32 { date.get(i..i + 2).map_or(0, |f| f.parse().unwrap()) };
+    let (year, month, day) = (2000 + field(0) as i64, field(2), field(4));
+    let (hour, minute, second) = (field(6), field(8), field(10));
+    if !(1..=12).contains(&month) || day == 0 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    if civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+    Some(days as u64 * 86400 + (hour * 3600 + minute * 60 + second) as u64)
+}
+
+/// Formats `time` as the `YYMMDD` date used by default in stamps.
+pub fn format_date(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    format!("{:02}{:02}{:02}", year % 100, month, day)
+}
+
+/// Mints a stamp for `resource` dated `date` by searching the counter over
+/// the base64 alphabet.
+pub fn mint<H: Hash + Default>(
+    resource: &str,
+    bits: u32,
+    extension: &str,
+    date: SystemTime,
+    thread_config: &ThreadConfig,
+) -> Result<Stamp, MintError> {
+    if resource.contains(':') || extension.contains(':') {
+        return Err(MintError::InvalidField);
+    }
+    let target_hash_mask =
+        leading_bits_mask::<H::Output>(bits as usize).ok_or(MintError::TooManyBits)?;
+
+    let mut rand = [0u8; 12];
+    getrandom::getrandom(&mut rand).map_err(MintError::Random)?;
+    let mut encoded_rand = Vec::new();
+    base64_encode(&rand, &mut encoded_rand);
+
+    let mut stamp = Stamp {
+        bits,
+        date: format_date(date),
+        resource: resource.to_string(),
+        extension: extension.to_string(),
+        rand: String::from_utf8(encoded_rand).expect("base64 is ascii"),
+        counter: String::new(),
+    };
+    let prefix = stamp.to_string();
+
+    // A counter of `bits / 6` characters covers about 2^bits candidates; each
+    // extra character makes running out 64 times less likely
+    let min_length = (bits as usize).div_ceil(6).max(1);
+    for length in min_length..min_length + 3 {
+        let plaintext_character_map = PlaintextTemplate::new()
+            .fixed(&prefix)
+            .charset(Charset::Base64, length)
+            .build();
+        let params = PowSearchParameters::<H> {
+            target_hash: H::Output::zero(),
+            target_hash_mask: target_hash_mask.clone(),
+            plaintext_character_map,
+        };
+        if let Some(solution) =
+            search_with_hasher(H::default(), params, thread_config)?.into_solution()?
+        {
+            stamp.counter = String::from_utf8(solution.plaintext[prefix.len()..].to_vec())
+                .expect("base64 is ascii");
+            return Ok(stamp);
+        }
+    }
+    Err(MintError::NotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{format_date, mint, parse_date, ParseStampError, Stamp, VerifyError};
+    use crate::{Sha1, Sha256, ThreadConfig};
+
+    #[test]
+    fn test_parse_and_check() {
+        // From the hashcash.org documentation
+        let stamp: Stamp = "1:20:060408:adam@cypherspace.org::1QTjaYd7niiQA/sc:ePa"
+            .parse()
+            .unwrap();
+        assert_eq!(stamp.bits, 20);
+        assert_eq!(stamp.resource, "adam@cypherspace.org");
+        assert!(stamp.check::<Sha1>());
+        assert_eq!(
+            Stamp::from_header(&stamp.to_header()).unwrap(),
+            stamp.clone()
+        );
+        assert_eq!(stamp.timestamp(), Some(1144454400), "2006-04-08T00:00:00Z");
+
+        let now = UNIX_EPOCH + Duration::from_secs(1144454400 + 3600);
+        let day = Duration::from_secs(86400);
+        assert_eq!(
+            stamp.verify::<Sha1>("adam@cypherspace.org", 20, now, day),
+            Ok(())
+        );
+        assert_eq!(
+            stamp.verify::<Sha1>("adam@cypherspace.org", 21, now, day),
+            Err(VerifyError::InsufficientBits)
+        );
+        assert_eq!(
+            stamp.verify::<Sha1>("eve@example.com", 20, now, day),
+            Err(VerifyError::ResourceMismatch)
+        );
+        assert_eq!(
+            stamp.verify::<Sha1>("adam@cypherspace.org", 20, now + 2 * day, day),
+            Err(VerifyError::Expired)
+        );
+        assert_eq!(
+            stamp.verify::<Sha1>("adam@cypherspace.org", 20, now - 3 * day, day),
+            Err(VerifyError::FutureDate)
+        );
+
+        assert_eq!(
+            "0:20:060408:adam@cypherspace.org:1QTjaYd7niiQA/sc".parse::<Stamp>(),
+            Err(ParseStampError::InvalidVersion)
+        );
+        assert_eq!(
+            Stamp::from_header("Subject: 1:20:060408:a::b:c"),
+            Err(ParseStampError::InvalidHeader)
+        );
+        assert_eq!(parse_date("060230"), None);
+        assert_eq!(parse_date("0604081200"), Some(1144454400 + 12 * 3600));
+    }
+
+    #[test]
+    fn test_mint() {
+        let thread_config = ThreadConfig {
+            thread_count: Some(2),
+            cancel: None,
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+        let now = UNIX_EPOCH + Duration::from_secs(1700000000);
+        assert_eq!(format_date(now), "231114");
+
+        let stamp = mint::<Sha1>("easy_pow", 12, "", now, &thread_config).unwrap();
+        assert!(stamp.check::<Sha1>());
+        assert_eq!(
+            stamp.verify::<Sha1>("easy_pow", 12, now, Duration::from_secs(86400)),
+            Ok(())
+        );
+        let stamp = mint::<Sha256>("easy_pow", 10, "a=b", now, &thread_config).unwrap();
+        assert!(stamp.check::<Sha256>());
+        assert!(mint::<Sha1>("a:b", 8, "", now, &thread_config).is_err());
+        assert!(mint::<Sha1>("a", 161, "", now, &thread_config).is_err());
+    }
+}
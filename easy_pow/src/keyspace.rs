@@ -0,0 +1,277 @@
+use std::ops::Range;
+
+use num_bigint::BigUint;
+
+use crate::{Alphabet, Enumerator};
+
+/// The plaintexts of a `plaintext_character_map`, numbered in the order
+/// searches enumerate them.
+///
+/// The index of a plaintext reads it as a mixed-radix number whose first
+/// position is the most significant digit, and sizes are exact however many
+/// positions there are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keyspace<A = Vec<u8>> {
+    plaintext_character_map: Vec<A>,
+    /// `suffix_sizes[pos]` is the number of plaintexts of the positions from
+    /// `pos` on, so the first entry is the size of the keyspace.
+    suffix_sizes: Vec<BigUint>,
+}
+
+impl<A: Alphabet> Keyspace<A> {
+    pub fn new(plaintext_character_map: Vec<A>) -> Self {
+        let mut suffix_sizes = vec![BigUint::from(1u32); plaintext_character_map.len() + 1];
+        for pos in (0..plaintext_character_map.len()).rev() {
+            suffix_sizes[pos] = &suffix_sizes[pos + 1] * plaintext_character_map[pos].len();
+        }
+        Self {
+            plaintext_character_map,
+            suffix_sizes,
+        }
+    }
+
+    pub fn plaintext_character_map(&self) -> &[A] {
+        &self.plaintext_character_map
+    }
+
+    /// The number of plaintexts.
+    pub fn size(&self) -> &BigUint {
+        &self.suffix_sizes[0]
+    }
+
+    /// The number of plaintexts, capped at `u128::MAX`. No search gets through
+    /// that many candidates, so searches index the capped keyspace with
+    /// `u128` and still visit candidates in the same order.
+    pub(crate) fn search_size(&self) -> u128 {
+        u128::try_from(self.size()).unwrap_or(u128::MAX)
+    }
+
+    /// The plaintext at `index`, or `None` past the last one.
+    pub fn index_to_plaintext(&self, index: &BigUint) -> Option<Vec<u8>> {
+        let mut plaintext = Vec::new();
+        for (d, chars) in self
+            .digits(index)?
+            .into_iter()
+            .zip(&self.plaintext_character_map)
+        {
+            chars.push_token(d, &mut plaintext);
+        }
+        Some(plaintext)
+    }
+
+    /// The index of `plaintext`, or `None` if it is not in the keyspace. A
+    /// plaintext that several token sequences spell counts as the one with
+    /// the lowest index, and a byte that occurs several times in an alphabet
+    /// as its first occurrence.
+    pub fn plaintext_to_index(&self, plaintext: &[u8]) -> Option<BigUint> {
+        let mut digits = Vec::with_capacity(self.plaintext_character_map.len());
+        if A::SINGLE_BYTE {
+            if plaintext.len() != self.plaintext_character_map.len() {
+                return None;
+            }
+            for (chars, c) in self.plaintext_character_map.iter().zip(plaintext) {
+                digits.push((0..chars.len()).position(|i| chars.byte(i) == *c)?);
+            }
+        } else if !self.spell(plaintext, &mut digits) {
+            return None;
+        }
+        let mut index = BigUint::ZERO;
+        for (chars, digit) in self.plaintext_character_map.iter().zip(digits) {
+            index = index * chars.len() + digit;
+        }
+        Some(index)
+    }
+
+    /// Finds the lowest token sequence that spells `plaintext` and appends
+    /// its digits to `digits`.
+    ///
+    /// A pass from the last position back marks the states (position, byte
+    /// offset) from which the remaining positions can spell the rest of
+    /// `plaintext`. A pass forward then takes the lowest token at each
+    /// position that leads to such a state, so the work grows with positions
+    /// times bytes rather than with the number of spellings.
+    fn spell(&self, plaintext: &[u8], digits: &mut Vec<usize>) -> bool {
+        let positions = self.plaintext_character_map.len();
+        let width = plaintext.len() + 1;
+        // `spells[pos * width + offset]` for positions `pos..` and bytes
+        // `offset..`
+        let mut spells = vec![false; (positions + 1) * width];
+        spells[positions * width + plaintext.len()] = true;
+        let mut token = Vec::new();
+        for pos in (0..positions).rev() {
+            let chars = &self.plaintext_character_map[pos];
+            for digit in 0..chars.len() {
+                token.clear();
+                chars.push_token(digit, &mut token);
+                for offset in 0..width.saturating_sub(token.len()) {
+                    if spells[(pos + 1) * width + offset + token.len()]
+                        && plaintext[offset..].starts_with(&token)
+                    {
+                        spells[pos * width + offset] = true;
+                    }
+                }
+            }
+        }
+        if !spells[0] {
+            return false;
+        }
+
+        let mut offset = 0;
+        for (pos, chars) in self.plaintext_character_map.iter().enumerate() {
+            let digit = (0..chars.len()).find(|&digit| {
+                token.clear();
+                chars.push_token(digit, &mut token);
+                plaintext[offset..].starts_with(&token)
+                    && spells[(pos + 1) * width + offset + token.len()]
+            });
+            digits.push(digit.expect("a reachable state has a next token"));
+            offset += token.len();
+        }
+        true
+    }
+
+    /// Walks the plaintexts of `range`, clipped to the keyspace.
+    pub fn range(&self, range: Range<BigUint>) -> KeyspaceRange<'_, A> {
+        let end = range.end.min(self.size().clone());
+        let remaining = if range.start < end {
+            end - &range.start
+        } else {
+            BigUint::ZERO
+        };
+        let mut enumerator = Enumerator::new(&self.plaintext_character_map);
+        if let Some(digits) = self.digits(&range.start) {
+            enumerator.set_indices(&digits);
+        }
+        KeyspaceRange {
+            enumerator,
+            remaining,
+        }
+    }
+
+    /// The `shard`-th of `shards` contiguous ranges of nearly equal size that
+    /// together cover the keyspace.
+    pub fn shard(&self, shard: usize, shards: usize) -> Range<BigUint> {
+        assert!(shard < shards, "shard {shard} out of {shards}");
+        let bound = |i: usize| self.size() * i / shards;
+        bound(shard)..bound(shard + 1)
+    }
+
+    pub fn enumerator(&self) -> Enumerator<'_, A> {
+        Enumerator::new(&self.plaintext_character_map)
+    }
+
+    /// The alphabet positions of the plaintext at `index`.
+    fn digits(&self, index: &BigUint) -> Option<Vec<usize>> {
+        if index >= self.size() {
+            return None;
+        }
+        Some(
+            self.plaintext_character_map
+                .iter()
+                .zip(&self.suffix_sizes[1..])
+                .map(|(chars, below)| {
+                    let digit = index / below % chars.len();
+                    usize::try_from(&digit).expect("digits are below the alphabet length")
+                })
+                .collect(),
+        )
+    }
+}
+
+/// The plaintexts of a range of a [`Keyspace`], in order.
+#[derive(Debug, Clone)]
+pub struct KeyspaceRange<'a, A = Vec<u8>> {
+    enumerator: Enumerator<'a, A>,
+    remaining: BigUint,
+}
+
+impl<A> KeyspaceRange<'_, A> {
+    /// The number of plaintexts left.
+    pub fn remaining(&self) -> &BigUint {
+        &self.remaining
+    }
+}
+
+impl<A: Alphabet> Iterator for KeyspaceRange<'_, A> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        if self.remaining == BigUint::ZERO {
+            return None;
+        }
+        self.remaining -= 1u32;
+        self.enumerator.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use super::Keyspace;
+    use crate::Tokens;
+
+    #[test]
+    fn test_keyspace() {
+        let keyspace = Keyspace::new(vec![
+            b"abc".to_vec(),
+            b"0123456789".to_vec(),
+            b"xy".to_vec(),
+        ]);
+        assert_eq!(*keyspace.size(), BigUint::from(60u32));
+        // (1 * 10 + 7) * 2 + 1
+        let index = BigUint::from(35u32);
+        assert_eq!(keyspace.plaintext_to_index(b"b7y"), Some(index.clone()));
+        assert_eq!(keyspace.index_to_plaintext(&index), Some(b"b7y".to_vec()));
+        assert_eq!(keyspace.index_to_plaintext(&60u32.into()), None);
+        assert_eq!(keyspace.plaintext_to_index(b"b7z"), None);
+        assert_eq!(keyspace.plaintext_to_index(b"b7"), None);
+
+        let range: Vec<Vec<u8>> = keyspace.range(34u32.into()..37u32.into()).collect();
+        assert_eq!(range, [&b"b7x"[..], b"b7y", b"b8x"]);
+        assert_eq!(keyspace.range(58u32.into()..100u32.into()).count(), 2);
+        assert_eq!(keyspace.range(70u32.into()..80u32.into()).count(), 0);
+        let shards: Vec<_> = (0..7).map(|i| keyspace.shard(i, 7)).collect();
+        assert_eq!(shards[0].start, BigUint::ZERO);
+        assert_eq!(shards[6].end, *keyspace.size());
+        assert!(shards.windows(2).all(|w| w[0].end == w[1].start));
+
+        // Exact beyond any machine integer
+        let keyspace = Keyspace::new(vec![(0..=255).collect::<Vec<u8>>(); 20]);
+        assert_eq!(*keyspace.size(), BigUint::from(1u32) << 160);
+        assert_eq!(keyspace.search_size(), u128::MAX);
+        let last = keyspace.size() - 1u32;
+        assert_eq!(keyspace.index_to_plaintext(&last), Some(vec![0xff; 20]));
+        assert_eq!(keyspace.plaintext_to_index(&[0xff; 20]), Some(last));
+        let mut range = keyspace.range(BigUint::from(1u32) << 152..BigUint::from(3u32) << 151);
+        assert_eq!(*range.remaining(), BigUint::from(1u32) << 151);
+        let mut expected = vec![0; 20];
+        expected[0] = 1;
+        assert_eq!(range.next(), Some(expected));
+
+        // Ambiguous spellings count as the lowest index
+        let keyspace = Keyspace::new(vec![Tokens::new(["a", "ab"]), Tokens::new(["b", "", "c"])]);
+        assert_eq!(*keyspace.size(), BigUint::from(6u32));
+        assert_eq!(keyspace.plaintext_to_index(b"ab"), Some(0u32.into()));
+        assert_eq!(keyspace.plaintext_to_index(b"abc"), Some(5u32.into()));
+        assert_eq!(keyspace.plaintext_to_index(b"ac"), Some(2u32.into()));
+        assert_eq!(keyspace.plaintext_to_index(b"abb"), Some(3u32.into()));
+        assert_eq!(keyspace.plaintext_to_index(b"b"), None);
+        assert_eq!(
+            keyspace.index_to_plaintext(&4u32.into()),
+            Some(b"ab".to_vec())
+        );
+        let range: Vec<Vec<u8>> = keyspace.range(2u32.into()..4u32.into()).collect();
+        assert_eq!(range, [&b"ac"[..], b"abb"]);
+
+        // Exponentially many spellings, none of which match
+        let keyspace = Keyspace::new(vec![Tokens::new(["a", "aa"]); 80]);
+        let mut plaintext = vec![b'a'; 80];
+        assert_eq!(keyspace.plaintext_to_index(&plaintext), Some(0u32.into()));
+        plaintext.push(b'b');
+        assert_eq!(keyspace.plaintext_to_index(&plaintext), None);
+        let keyspace = Keyspace::new(vec![Tokens::new(["a", "aa"]); 3]);
+        assert_eq!(keyspace.plaintext_to_index(b"aaaa"), Some(1u32.into()));
+        assert_eq!(keyspace.plaintext_to_index(b"aaaaaaa"), None);
+    }
+}
@@ -1,9 +1,13 @@
 use std::{
+    cell::Cell,
+    ops::Range,
+    panic::{self, AssertUnwindSafe},
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
     thread,
+    time::{Duration, Instant},
 };
 
 #[cfg(feature = "backend-openssl")]
@@ -13,7 +17,35 @@ pub mod openssl;
 #[doc(hidden)]
 pub mod rust_crypto;
 
+mod affine;
+mod alphabet;
+#[cfg(feature = "backend-rust-crypto")]
+pub mod bitcoin;
+mod cancel;
+mod checksum;
+mod collision;
+mod encoding;
+mod enumerator;
+mod error;
+#[cfg(feature = "backend-rust-crypto")]
+pub mod git;
+mod handle;
 mod hash_type;
+pub mod hashcash;
+mod keyspace;
+mod mask;
+#[cfg(feature = "memory-hard")]
+mod memory_hard;
+mod pipeline;
+mod progress;
+mod report;
+mod rule;
+mod scheduler;
+pub mod sloth;
+mod solver;
+mod template;
+pub mod timelock;
+mod wordlist;
 
 #[cfg(feature = "backend-rust-crypto")]
 pub use rust_crypto::{Md5, Sha1, Sha224, Sha256, Sha384, Sha512};
@@ -21,9 +53,33 @@ pub use rust_crypto::{Md5, Sha1, Sha224, Sha256, Sha384, Sha512};
 #[cfg(all(not(feature = "backend-rust-crypto"), feature = "backend-openssl"))]
 pub use openssl::{Md5, Sha1, Sha224, Sha256, Sha384, Sha512};
 
+pub use affine::{search_affine, AffineHash};
+pub use alphabet::{Alphabet, AnyAlphabet, Tokens};
+pub use cancel::CancellationToken;
+pub use checksum::{Adler32, Crc32, Crc64, Fnv1a32, Fnv1a64, Murmur3, XxHash32, XxHash64};
+pub use collision::{search_collision, Collision, CollisionParameters};
+pub use enumerator::Enumerator;
+pub use error::{HashError, PowError};
+#[cfg(feature = "async")]
+pub use handle::{search_async, search_by_hash_type_async, search_with_hasher_async};
+pub use handle::{spawn_search, spawn_search_by_hash_type, spawn_search_with_hasher, SearchHandle};
 pub use hash_type::{HashType, InvalidHashTypeError};
+pub use keyspace::{Keyspace, KeyspaceRange};
+pub use mask::{parse_hcmask, read_hcmask, search_masks_by_hash_type, HcmaskError, MaskError};
+#[cfg(feature = "memory-hard")]
+pub use memory_hard::{Argon2id, Bcrypt, InvalidCostParametersError, Scrypt};
+pub use pipeline::{ParsePipelineError, Pipeline, PipelineHash};
+pub use progress::SearchProgress;
+pub use report::{SearchOutcome, SearchReport, Solution};
+pub use rule::{parse_rules, read_rules, Rule, RuleError, RuleFileError};
+pub use solver::Solver;
+pub use template::{Charset, InvalidCharsetError, PlaintextTemplate};
+pub use wordlist::{hybrid_mask_wordlist, hybrid_wordlist_mask, Wordlist};
+
+use affine::search_affine_with;
+use scheduler::{LocalQueue, Scheduler};
 
-pub trait HashOutput: Sync + Send + Clone + Sized {
+pub trait HashOutput: Sync + Send + Clone + Sized + std::fmt::Debug {
     const HASH_BYTES: usize;
 
     fn as_slice(&self) -> &[u8];
@@ -58,26 +114,69 @@ impl<const N: usize> HashOutput for [u8; N] {
     }
 }
 
+/// A mask selecting the first `bits` bits of a hash, or `None` if the hash
+/// has fewer bits.
+pub(crate) fn leading_bits_mask<O: HashOutput>(bits: usize) -> Option<O> {
+    if bits > O::HASH_BYTES * 8 {
+        return None;
+    }
+    let mut mask = O::zero();
+    let slice = mask.as_slice_mut();
+    slice[..bits / 8].fill(0xff);
+    if !bits.is_multiple_of(8) {
+        slice[bits / 8] = !(0xff >> (bits % 8));
+    }
+    Some(mask)
+}
+
 /// The trait representing the hash function used in Pow (Proof of Work)
-pub trait Hash {
+///
+/// The hasher is shared by every worker thread, so implementations that need
+/// configuration (e.g. a [`PipelineHash`]) keep it in `self`.
+pub trait Hash: Sync + Send {
     /// Hash output type
     type Output: HashOutput;
-    /// When executing in parallel, the maximum size for pushing to a task queue.
+    /// The number of candidates in a worker's first work unit. Later units
+    /// grow or shrink until each takes about a millisecond.
     const PARALLEL_BLOCK_SIZE: usize;
+    /// How candidates are distributed to the worker threads.
+    const WORK_DISTRIBUTION: WorkDistribution = WorkDistribution::Block;
 
     /// The hash function
-    fn hash(bytes: &[u8]) -> Self::Output;
+    fn hash(&self, bytes: &[u8]) -> Self::Output;
+
+    /// The hash function for backends that can fail. Searches call this and
+    /// stop with [`PowError::Hash`] on failure.
+    fn try_hash(&self, bytes: &[u8]) -> Result<Self::Output, HashError> {
+        Ok(self.hash(bytes))
+    }
 }
 
+/// The strategy used by [`search`] to size the work units of the workers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkDistribution {
+    /// Units of consecutive candidates, sized to the speed of the hash
+    /// function. Suited to most hash functions.
+    Block,
+    /// Units of a single candidate, with progress reported after each hash.
+    /// Suited to slow hash functions (e.g. memory-hard ones) where even a
+    /// second candidate would delay the end of the search noticeably.
+    SingleCandidate,
+}
+
+/// What to search for and where.
+///
+/// `plaintext_character_map` has one [`Alphabet`] per position: bytes by
+/// default, or [`Tokens`] for multi-byte choices.
 #[derive(Debug, Clone)]
-pub struct PowSearchParameters<H: Hash> {
+pub struct PowSearchParameters<H: Hash, A = Vec<u8>> {
     pub target_hash: H::Output,
     pub target_hash_mask: H::Output,
 
-    pub plaintext_character_map: Vec<Vec<u8>>,
+    pub plaintext_character_map: Vec<A>,
 }
 
-impl<H: Hash> PowSearchParameters<H> {
+impl<H: Hash, A> PowSearchParameters<H, A> {
     #[inline]
     fn check_hash(&self, hash: &H::Output) -> bool {
         let hash = hash.as_slice();
@@ -93,203 +192,580 @@ impl<H: Hash> PowSearchParameters<H> {
     }
 }
 
-struct PowSearcher<H: Hash> {
-    to_search_thread: crossbeam::channel::Sender<(Vec<u8>, usize)>,
-    from_search_thread: crossbeam::channel::Receiver<Vec<u8>>,
+/// The number of candidates a worker hashes between two checks of
+/// [`ThreadConfig::cancel`].
+pub const CANCEL_CHECK_INTERVAL: usize = 4096;
+
+/// A matching plaintext and its index in the keyspace.
+///
+/// Indices count candidates in the order of enumeration, so in deterministic
+/// mode the hit with the lowest index is the lexicographically smallest
+/// solution.
+#[derive(Debug)]
+struct Hit<O> {
+    index: u128,
+    plaintext: Vec<u8>,
+    digest: O,
+}
 
-    parameter: Arc<PowSearchParameters<H>>,
-    message_count: Vec<usize>,
-    search_end: Arc<AtomicBool>,
+/// Keeps whichever of `slot` and `hit` comes first in enumeration order.
+fn keep_lowest<O>(slot: &mut Option<Hit<O>>, hit: Hit<O>) {
+    if slot.as_ref().is_none_or(|lowest| hit.index < lowest.index) {
+        *slot = Some(hit);
+    }
 }
 
-struct PowSearcherWorker<H: Hash> {
-    from_search_thread: crossbeam::channel::Receiver<(Vec<u8>, usize)>,
-    to_search_thread: crossbeam::channel::Sender<Vec<u8>>,
-    search_end: Arc<AtomicBool>,
-    parameter: Arc<PowSearchParameters<H>>,
+/// How long a work unit should take. Units grow or shrink towards it, so
+/// fast hash functions get large units and slow ones single candidates.
+const UNIT_TIME: Duration = Duration::from_millis(1);
+
+/// The largest work unit, which keeps a unit of a fast hash function from
+/// outlasting a cancellation by much.
+const MAX_UNIT: u128 = 1 << 24;
+
+/// What the workers of one search share.
+struct SearchState<H: Hash, A> {
+    hasher: H,
+    parameter: PowSearchParameters<H, A>,
+    keyspace: Keyspace<A>,
+    scheduler: Scheduler,
+    search_end: AtomicBool,
+    cancel: Option<CancellationToken>,
+    progress: Option<SearchProgress>,
+    deterministic: bool,
+    found: Mutex<Option<Hit<H::Output>>>,
+    /// The first error, which ends the search.
+    failure: Mutex<Option<PowError>>,
 }
 
-impl<H: Hash> PowSearcherWorker<H> {
-    fn search(&self, current_plaintext: &mut Vec<u8>, pos: usize) -> Result<(), SearchError> {
-        if pos == current_plaintext.len() {
-            if self.parameter.check_hash(&H::hash(&current_plaintext)) {
-                return Err(SearchError::Found(current_plaintext.clone()));
-            }
-            Ok(())
-        } else {
-            for &c in &self.parameter.plaintext_character_map[pos] {
-                current_plaintext[pos] = c;
-                self.search(current_plaintext, pos + 1)?;
-                if self.search_end.load(Ordering::Relaxed) {
-                    break;
-                }
-            }
-            current_plaintext[pos] = 0;
-            Ok(())
+impl<H: Hash, A> SearchState<H, A> {
+    /// The index below which units are still worth searching.
+    fn limit(&self) -> u128 {
+        if !self.deterministic {
+            return u128::MAX;
         }
+        // The hit itself has been hashed already
+        self.found
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(u128::MAX, |hit| hit.index)
     }
 
-    fn search_thread(&self) {
-        while let Ok((mut plaintext, pos)) = self.from_search_thread.recv() {
-            match self.search(&mut plaintext, pos) {
-                Ok(_) => {}
-                Err(SearchError::Found(ret)) => {
-                    let _ = self.to_search_thread.try_send(ret);
-                    self.search_end.store(true, Ordering::Relaxed);
-                }
-                Err(SearchError::ThreadChannelError) => {
-                    break;
-                }
-            }
+    /// Ends the search with `hit`, or in deterministic mode keeps it if it is
+    /// the lowest so far and lets the search of lower units go on.
+    fn found(&self, hit: Hit<H::Output>) {
+        let mut found = self.found.lock().unwrap();
+        if self.deterministic {
+            keep_lowest(&mut found, hit);
+        } else if found.is_none() {
+            *found = Some(hit);
+            self.search_end.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn fail(&self, error: PowError) {
+        self.failure.lock().unwrap().get_or_insert(error);
+        self.search_end.store(true, Ordering::Relaxed);
+    }
+
+    /// The result of the search once every worker has finished.
+    fn report(
+        &self,
+        thread_hashes: Vec<u64>,
+        start: Instant,
+    ) -> Result<SearchReport<H::Output>, PowError> {
+        if let Some(e) = self.failure.lock().unwrap().take() {
+            return Err(e);
         }
+        let cancelled = self.cancel.as_ref().is_some_and(|c| c.is_cancelled());
+        let outcome = match self.found.lock().unwrap().take() {
+            // A hit is only known to be the lowest once every earlier unit ran
+            Some(_) if cancelled && self.deterministic => SearchOutcome::Cancelled,
+            Some(hit) => {
+                SearchOutcome::Found(Solution::new(hit.plaintext, hit.digest, hit.index.into()))
+            }
+            None if cancelled => SearchOutcome::Cancelled,
+            None => SearchOutcome::NotFound,
+        };
+        Ok(SearchReport::new(outcome, thread_hashes, start.elapsed()))
     }
 }
 
-#[derive(thiserror::Error, Debug)]
-enum SearchError {
-    #[error("Hash found")]
-    Found(Vec<u8>),
-    #[error("The thread channel is closed")]
-    ThreadChannelError,
+struct PowSearcherWorker<H: Hash, A> {
+    state: Arc<SearchState<H, A>>,
+    queue: LocalQueue,
+    /// Candidates hashed since the cancellation token was last checked.
+    unchecked: Cell<usize>,
+    hashes: Cell<u64>,
+    /// Hashes already added to the progress.
+    flushed: Cell<u64>,
 }
 
-impl<H: Hash> PowSearcher<H> {
-    fn search(&self, current_plaintext: &mut Vec<u8>, pos: usize) -> Result<(), SearchError> {
-        if pos == current_plaintext.len() {
-            if self.parameter.check_hash(&H::hash(&current_plaintext)) {
-                return Err(SearchError::Found(current_plaintext.clone()));
+impl<H: Hash, A: Alphabet> PowSearcherWorker<H, A> {
+    /// Whether the search has ended, checking the cancellation token every
+    /// [`CANCEL_CHECK_INTERVAL`] calls.
+    fn should_stop(&self) -> bool {
+        if self.state.search_end.load(Ordering::Relaxed) {
+            return true;
+        }
+        let unchecked = self.unchecked.get() + 1;
+        if unchecked < CANCEL_CHECK_INTERVAL {
+            self.unchecked.set(unchecked);
+            return false;
+        }
+        self.unchecked.set(0);
+        self.flush_progress();
+        if self.state.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+            self.state.search_end.store(true, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    fn flush_progress(&self) {
+        if let Some(progress) = &self.state.progress {
+            let hashes = self.hashes.get();
+            if hashes != self.flushed.get() {
+                progress.add(hashes - self.flushed.get());
+                self.flushed.set(hashes);
             }
-            Ok(())
-        } else if self.message_count[pos] <= H::PARALLEL_BLOCK_SIZE {
-            crossbeam::channel::select! {
-                recv(self.from_search_thread) -> ret => {
-                    match ret {
-                        Ok(ret) => return Err(SearchError::Found(ret)),
-                        Err(_) => {
-                            eprintln!("Thread recv error!!!");
-                            return Err(SearchError::ThreadChannelError);
-                        }
-                    }
-                },
-                send(self.to_search_thread, (current_plaintext.clone(), pos)) -> ret => {
-                    if ret.is_err() {
-                        eprintln!("Thread send error");
-                        return Err(SearchError::ThreadChannelError);
-                    }
-                }
+        }
+    }
+
+    /// Hashes the candidates of `unit` in order up to the first hit.
+    fn search(
+        &self,
+        unit: Range<u128>,
+        enumerator: &mut Enumerator<A>,
+    ) -> Result<Option<Hit<H::Output>>, PowError> {
+        enumerator.seek(unit.start);
+        for index in unit {
+            let plaintext = enumerator.plaintext().expect("units lie in the keyspace");
+            let hash = self.state.hasher.try_hash(plaintext)?;
+            self.hashes.set(self.hashes.get() + 1);
+            if self.state.parameter.check_hash(&hash) {
+                return Ok(Some(Hit {
+                    index,
+                    plaintext: plaintext.to_vec(),
+                    digest: hash,
+                }));
+            }
+            if self.should_stop() {
+                break;
+            }
+            enumerator.advance();
+        }
+        Ok(None)
+    }
+
+    fn search_thread(&self) {
+        // The only buffers of the worker, reused for every unit
+        let mut enumerator = self.state.keyspace.enumerator();
+        let max_unit = match H::WORK_DISTRIBUTION {
+            WorkDistribution::Block => MAX_UNIT,
+            WorkDistribution::SingleCandidate => 1,
+        };
+        let mut unit_size = (H::PARALLEL_BLOCK_SIZE as u128).clamp(1, max_unit);
+        while !self.state.search_end.load(Ordering::Relaxed) {
+            let limit = self.state.limit();
+            let Some(unit) = self.state.scheduler.next(&self.queue, unit_size, limit) else {
+                break;
             };
-            Ok(())
-        } else {
-            for &c in &self.parameter.plaintext_character_map[pos] {
-                current_plaintext[pos] = c;
-                self.search(current_plaintext, pos + 1)?;
-                if self.search_end.load(Ordering::Relaxed) {
+            let start = Instant::now();
+            match self.search(unit, &mut enumerator) {
+                Ok(Some(hit)) => self.state.found(hit),
+                Ok(None) => {}
+                Err(e) => {
+                    self.state.fail(e);
                     break;
                 }
             }
-            current_plaintext[pos] = 0;
-            Ok(())
+            self.flush_progress();
+            let elapsed = start.elapsed();
+            if elapsed < UNIT_TIME / 2 {
+                unit_size = (unit_size * 2).min(max_unit);
+            } else if elapsed > UNIT_TIME * 2 {
+                unit_size = (unit_size / 2).max(1);
+            }
         }
     }
 
-    fn run_search(self) -> Result<(), SearchError> {
-        let mut plaintext = vec![0; self.parameter.plaintext_character_map.len()];
-        self.search(&mut plaintext, 0)?;
-        drop(self.to_search_thread);
-        while let Ok(ret) = self.from_search_thread.recv() {
-            return Err(SearchError::Found(ret));
+    /// Runs the worker, recording a panic as the outcome of the search
+    /// instead of letting it tear down the thread. Returns the number of
+    /// hashes computed.
+    fn run(self) -> u64 {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| self.search_thread()));
+        if let Err(payload) = result {
+            self.state.fail(PowError::from_panic(payload));
         }
-        Ok(())
+        self.flush_progress();
+        self.hashes.get()
     }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct ThreadConfig {
     pub thread_count: Option<usize>,
-    pub cancel: Option<Arc<AtomicBool>>,
+    /// Stops the search with [`SearchOutcome::Cancelled`] when cancelled.
+    pub cancel: Option<CancellationToken>,
+    /// Returns the lexicographically smallest solution, the one a single
+    /// thread would find, instead of whichever thread finds one first.
+    /// Threads then finish every work unit before the first hit.
+    pub deterministic: bool,
+    /// Counts the hashes computed while the search runs.
+    pub progress: Option<SearchProgress>,
+    /// On a [`Solver`], workers of searches with a higher priority get a
+    /// thread first. Searches on threads of their own ignore it.
+    pub priority: i32,
+}
+
+/// Searches a plaintext whose hash matches `params`.
+///
+/// Not finding a match is reported as [`SearchOutcome::NotFound`]; errors
+/// are reserved for searches that could not run to an outcome.
+pub fn search<H: Hash + Default>(
+    params: PowSearchParameters<H, impl Alphabet>,
+    thread_config: &ThreadConfig,
+) -> Result<SearchReport<H::Output>, PowError> {
+    search_with_hasher(H::default(), params, thread_config)
+}
+
+/// Same as [`search`], but uses the given hasher instance.
+///
+/// This is required for hash functions that carry configuration, such as a
+/// [`PipelineHash`].
+pub fn search_with_hasher<H: Hash>(
+    hasher: H,
+    params: PowSearchParameters<H, impl Alphabet>,
+    thread_config: &ThreadConfig,
+) -> Result<SearchReport<H::Output>, PowError> {
+    let start = Instant::now();
+    check_alphabets(&params.plaintext_character_map)?;
+    let thread_count = OwnThreads.worker_count(thread_config);
+    thread::scope(|s| {
+        search_on(hasher, params, thread_config, thread_count, start, |job| {
+            s.spawn(job);
+        })
+    })
 }
 
-pub enum SearchResult {
-    Found(Vec<u8>),
-    NotFound,
-    UnexpectedError,
-    InvalidTargetHashLength,
-    InvalidTargetHashMaskLength,
+/// A worker's share of a search, run on a thread picked by the caller of
+/// [`start_search_on`].
+pub(crate) type Job<'a> = Box<dyn FnOnce() + Send + 'a>;
+
+/// Receives the result of a search started by [`start_search_on`] or a
+/// [`Runner`].
+pub(crate) type OnDone<'a, O> = Box<dyn FnOnce(Result<SearchReport<O>, PowError>) + Send + 'a>;
+
+/// Runs `start` with a sender for the result and waits for it. A sender
+/// dropped without sending, because its search was lost, is reported as
+/// [`PowError::ChannelClosed`].
+pub(crate) fn wait_for_done<T>(
+    start: impl FnOnce(crossbeam::channel::Sender<T>),
+) -> Result<T, PowError> {
+    let (done_tx, done_rx) = crossbeam::channel::bounded(1);
+    start(done_tx);
+    done_rx.recv().map_err(|_| PowError::ChannelClosed)
+}
+
+/// Same as [`start_search_on`], but the calling thread waits for the result.
+pub(crate) fn search_on<'a, H: Hash + 'a, A: Alphabet + 'a>(
+    hasher: H,
+    params: PowSearchParameters<H, A>,
+    thread_config: &ThreadConfig,
+    thread_count: usize,
+    start: Instant,
+    spawn: impl Fn(Job<'a>),
+) -> Result<SearchReport<H::Output>, PowError>
+where
+    H::Output: 'a,
+{
+    wait_for_done(|done| {
+        start_search_on(
+            hasher,
+            params,
+            thread_config,
+            thread_count,
+            start,
+            spawn,
+            move |result| {
+                let _ = done.send(result);
+            },
+        )
+    })?
 }
 
-pub fn search<H: Hash>(
-    params: PowSearchParameters<H>,
+/// The workers of a search that have yet to finish, and what to do once
+/// none are left.
+struct Completion<'a, O> {
+    remaining: usize,
+    thread_hashes: Vec<u64>,
+    on_done: Option<OnDone<'a, O>>,
+}
+
+/// Starts a search with `thread_count` workers started by `spawn`, which take
+/// work units from each other's queues, and returns without waiting for
+/// them. The last worker to finish passes the report to `on_done`.
+///
+/// Nothing waits on the threads that run the workers, so `spawn` may run them
+/// on threads that outlive the search. If it drops a job instead of running
+/// it, `on_done` is dropped without being called.
+pub(crate) fn start_search_on<'a, H: Hash + 'a, A: Alphabet + 'a>(
+    hasher: H,
+    mut params: PowSearchParameters<H, A>,
     thread_config: &ThreadConfig,
-) -> SearchResult {
-    let thread_count = thread_config.thread_count.unwrap_or_else(|| {
-        std::thread::available_parallelism()
-            .map(|a| a.get())
-            .unwrap_or(2)
+    thread_count: usize,
+    start: Instant,
+    spawn: impl Fn(Job<'a>),
+    on_done: impl FnOnce(Result<SearchReport<H::Output>, PowError>) + Send + 'a,
+) where
+    H::Output: 'a,
+{
+    let thread_count = thread_count.max(1);
+    // The keyspace takes the alphabets, which may be large word lists
+    let keyspace = Keyspace::new(std::mem::take(&mut params.plaintext_character_map));
+    let (scheduler, queues) = Scheduler::new(keyspace.search_size(), thread_count);
+    let state = Arc::new(SearchState {
+        hasher,
+        parameter: params,
+        keyspace,
+        scheduler,
+        search_end: AtomicBool::new(false),
+        cancel: thread_config.cancel.clone(),
+        progress: thread_config.progress.clone(),
+        deterministic: thread_config.deterministic,
+        found: Mutex::new(None),
+        failure: Mutex::new(None),
     });
-    let params = Arc::new(params);
-    thread::scope(|s: &thread::Scope<'_, '_>| {
-        let (from_main_thread_tx, from_main_thread_rx) = crossbeam::channel::bounded(thread_count);
-        let (from_worker_thread_tx, from_worker_thread_rx) =
-            crossbeam::channel::bounded(thread_count);
-        let search_end = thread_config
-            .cancel
-            .clone()
-            .unwrap_or_else(|| Arc::new(AtomicBool::new(false)));
-
-        for _ in 0..thread_count {
-            let worker = PowSearcherWorker {
-                from_search_thread: from_worker_thread_rx.clone(),
-                to_search_thread: from_main_thread_tx.clone(),
-                search_end: search_end.clone(),
-                parameter: params.clone(),
-            };
-            s.spawn(move || worker.search_thread());
-        }
-        drop(from_worker_thread_rx);
-        drop(from_main_thread_tx);
-
-        let searcher = PowSearcher {
-            to_search_thread: from_worker_thread_tx,
-            from_search_thread: from_main_thread_rx,
-            message_count: get_message_count(&params.plaintext_character_map),
-            parameter: params,
-            search_end,
-        };
+    // The calling thread hashes nothing; its entry stays 0
+    let completion = Arc::new(Mutex::new(Completion {
+        remaining: thread_count,
+        thread_hashes: vec![0; thread_count + 1],
+        on_done: Some(Box::new(on_done)),
+    }));
 
-        match searcher.run_search() {
-            Ok(_) => SearchResult::NotFound,
-            Err(SearchError::Found(ret)) => SearchResult::Found(ret),
-            Err(_) => SearchResult::UnexpectedError,
-        }
-    })
+    for (offset, queue) in queues.into_iter().enumerate() {
+        let worker = PowSearcherWorker {
+            state: state.clone(),
+            queue,
+            unchecked: Cell::new(0),
+            hashes: Cell::new(0),
+            flushed: Cell::new(0),
+        };
+        let (state, completion) = (state.clone(), completion.clone());
+        spawn(Box::new(move || {
+            let hashes =
+                panic::catch_unwind(AssertUnwindSafe(|| worker.run())).unwrap_or_else(|payload| {
+                    state.fail(PowError::from_panic(payload));
+                    0
+                });
+            let finished = {
+                let mut completion = completion.lock().unwrap();
+                completion.thread_hashes[offset + 1] = hashes;
+                completion.remaining -= 1;
+                match completion.remaining {
+                    0 => completion
+                        .on_done
+                        .take()
+                        .map(|on_done| (on_done, std::mem::take(&mut completion.thread_hashes))),
+                    _ => None,
+                }
+            };
+            if let Some((on_done, thread_hashes)) = finished {
+                on_done(state.report(thread_hashes, start));
+            }
+        }));
+    }
 }
 
 pub fn search_by_hash_type(
     hash_type: HashType,
     target_hash: &[u8],
     target_hash_mask: &[u8],
-    plaintext_character_map: &[Vec<u8>],
+    plaintext_character_map: &[impl Alphabet + Clone + 'static],
+    thread_config: &ThreadConfig,
+) -> Result<SearchReport<Vec<u8>>, PowError> {
+    search_by_hash_type_on(
+        &OwnThreads,
+        hash_type,
+        target_hash,
+        target_hash_mask,
+        plaintext_character_map,
+        thread_config,
+    )
+}
+
+/// Where [`start_search_by_hash_type_on`] runs the search it picks.
+pub(crate) trait Runner {
+    /// Runs a search and passes its result to `on_done`, either before
+    /// returning or later from one of the runner's threads.
+    fn start<H: Hash + 'static, A: Alphabet + 'static>(
+        &self,
+        hasher: H,
+        params: PowSearchParameters<H, A>,
+        thread_config: &ThreadConfig,
+        on_done: impl FnOnce(Result<SearchReport<H::Output>, PowError>) + Send + 'static,
+    );
+
+    /// The number of workers that [`start`](Self::start) gives a search.
+    fn worker_count(&self, thread_config: &ThreadConfig) -> usize;
+}
+
+/// Runs searches on threads of their own, like [`search_with_hasher`].
+pub(crate) struct OwnThreads;
+
+impl Runner for OwnThreads {
+    fn worker_count(&self, thread_config: &ThreadConfig) -> usize {
+        thread_config
+            .thread_count
+            .unwrap_or_else(|| {
+                std::thread::available_parallelism()
+                    .map(|a| a.get())
+                    .unwrap_or(2)
+            })
+            .max(1)
+    }
+
+    fn start<H: Hash + 'static, A: Alphabet + 'static>(
+        &self,
+        hasher: H,
+        params: PowSearchParameters<H, A>,
+        thread_config: &ThreadConfig,
+        on_done: impl FnOnce(Result<SearchReport<H::Output>, PowError>) + Send + 'static,
+    ) {
+        on_done(search_with_hasher(hasher, params, thread_config))
+    }
+}
+
+/// Same as [`start_search_by_hash_type_on`], but the calling thread waits for
+/// the result.
+pub(crate) fn search_by_hash_type_on<A: Alphabet + Clone + 'static>(
+    runner: &impl Runner,
+    hash_type: HashType,
+    target_hash: &[u8],
+    target_hash_mask: &[u8],
+    plaintext_character_map: &[A],
+    thread_config: &ThreadConfig,
+) -> Result<SearchReport<Vec<u8>>, PowError> {
+    wait_for_done(|done| {
+        start_search_by_hash_type_on(
+            runner,
+            hash_type,
+            target_hash,
+            target_hash_mask,
+            plaintext_character_map,
+            thread_config,
+            move |result| {
+                let _ = done.send(result);
+            },
+        )
+    })?
+}
+
+/// Picks the hash function of `hash_type` and starts the search on `runner`,
+/// which passes the result to `on_done`.
+pub(crate) fn start_search_by_hash_type_on<A: Alphabet + Clone + 'static>(
+    runner: &impl Runner,
+    hash_type: HashType,
+    target_hash: &[u8],
+    target_hash_mask: &[u8],
+    plaintext_character_map: &[A],
     thread_config: &ThreadConfig,
-) -> SearchResult {
+    on_done: impl FnOnce(Result<SearchReport<Vec<u8>>, PowError>) + Send + 'static,
+) {
     macro_rules! hash_type_impl {
-        ($hash_type: ty) => {{
-            let target_hash = <$hash_type as Hash>::Output::from_slice(target_hash);
-            if target_hash.is_none() {
-                return SearchResult::InvalidTargetHashLength;
-            }
-            let target_hash_mask = <$hash_type as Hash>::Output::from_slice(target_hash_mask);
-            if target_hash_mask.is_none() {
-                return SearchResult::InvalidTargetHashMaskLength;
+        ($hash_type: ty) => {
+            hash_type_impl!($hash_type, <$hash_type>::default())
+        };
+        ($hash_type: ty, $hasher: expr) => {
+            hash_type_impl!(
+                $hash_type,
+                $hasher,
+                |hasher, params, thread_config, on_done| {
+                    runner.start(hasher, params, thread_config, on_done)
+                }
+            )
+        };
+        (affine $hash_type: ty) => {{
+            // The linear solve works on single bytes only, and its solution is
+            // not the smallest one that deterministic mode promises
+            let single_bytes = match thread_config.deterministic {
+                true => None,
+                false => plaintext_character_map
+                    .iter()
+                    .map(Alphabet::single_bytes)
+                    .collect::<Option<Vec<_>>>(),
+            };
+            match single_bytes {
+                Some(plaintext_character_map) => hash_type_impl!(
+                    $hash_type,
+                    <$hash_type>::default(),
+                    |hasher, params, thread_config, on_done| {
+                        // The linear solve needs no threads; only the fallback does
+                        search_affine_with(
+                            hasher,
+                            params,
+                            thread_config,
+                            runner.worker_count(thread_config),
+                            |hasher, params, thread_config, on_done| {
+                                runner.start(hasher, params, thread_config, on_done)
+                            },
+                            on_done,
+                        )
+                    },
+                    plaintext_character_map
+                ),
+                None => hash_type_impl!($hash_type),
             }
+        }};
+        ($hash_type: ty, $hasher: expr, $start: expr) => {
+            hash_type_impl!($hash_type, $hasher, $start, plaintext_character_map)
+        };
+        ($hash_type: ty, $hasher: expr, $start: expr, $plaintext_character_map: expr) => {{
+            type Output = <$hash_type as Hash>::Output;
+            let Some(target_hash) = Output::from_slice(target_hash) else {
+                return on_done(Err(PowError::TargetHashLength {
+                    expected: Output::HASH_BYTES,
+                    actual: target_hash.len(),
+                }));
+            };
+            let Some(target_hash_mask) = Output::from_slice(target_hash_mask) else {
+                return on_done(Err(PowError::TargetHashMaskLength {
+                    expected: Output::HASH_BYTES,
+                    actual: target_hash_mask.len(),
+                }));
+            };
 
-            let search_param = PowSearchParameters::<$hash_type> {
-                plaintext_character_map: plaintext_character_map.to_vec(),
-                target_hash: target_hash.unwrap(),
-                target_hash_mask: target_hash_mask.unwrap(),
+            let search_param = PowSearchParameters {
+                plaintext_character_map: $plaintext_character_map.to_vec(),
+                target_hash,
+                target_hash_mask,
             };
-            search(search_param, thread_config)
+            $start(
+                $hasher,
+                search_param,
+                thread_config,
+                move |result: Result<SearchReport<Output>, PowError>| {
+                    on_done(result.map(SearchReport::into_bytes))
+                },
+            )
         }};
     }
+    macro_rules! pipeline_impl {
+        ($pipeline: expr, $($len: literal),*) => {
+            match $pipeline.output_len() {
+                $($len => hash_type_impl!(
+                    PipelineHash<[u8; $len]>,
+                    PipelineHash::new($pipeline).expect("output length is checked")
+                ),)*
+                len => on_done(Err(PowError::TargetHashLength {
+                    expected: len,
+                    actual: target_hash.len(),
+                })),
+            }
+        };
+    }
     match hash_type {
         HashType::Md5 => hash_type_impl!(Md5),
         HashType::Sha1 => hash_type_impl!(Sha1),
@@ -297,20 +773,23 @@ pub fn search_by_hash_type(
         HashType::Sha256 => hash_type_impl!(Sha256),
         HashType::Sha384 => hash_type_impl!(Sha384),
         HashType::Sha512 => hash_type_impl!(Sha512),
+        HashType::Crc32 => hash_type_impl!(affine Crc32),
+        HashType::Crc64 => hash_type_impl!(affine Crc64),
+        HashType::Adler32 => hash_type_impl!(Adler32),
+        HashType::XxHash32 => hash_type_impl!(XxHash32),
+        HashType::XxHash64 => hash_type_impl!(XxHash64),
+        HashType::Fnv1a32 => hash_type_impl!(Fnv1a32),
+        HashType::Fnv1a64 => hash_type_impl!(Fnv1a64),
+        HashType::Murmur3 => hash_type_impl!(Murmur3),
+        HashType::Pipeline(pipeline) => pipeline_impl!(pipeline, 4, 8, 16, 20, 28, 32, 48, 64),
     }
 }
 
-fn get_message_count(plaintext_character_map: &[Vec<u8>]) -> Vec<usize> {
-    let mut message_count = vec![0; plaintext_character_map.len()];
-    if !plaintext_character_map.is_empty() {
-        message_count[plaintext_character_map.len() - 1] =
-            plaintext_character_map[plaintext_character_map.len() - 1].len();
-        for i in (0..plaintext_character_map.len() - 1).rev() {
-            message_count[i] =
-                message_count[i + 1].saturating_mul(plaintext_character_map[i].len());
-        }
+pub(crate) fn check_alphabets<A: Alphabet>(plaintext_character_map: &[A]) -> Result<(), PowError> {
+    match plaintext_character_map.iter().position(|c| c.is_empty()) {
+        Some(position) => Err(PowError::EmptyAlphabet { position }),
+        None => Ok(()),
     }
-    message_count
 }
 
 #[cfg(all(
@@ -318,8 +797,14 @@ fn get_message_count(plaintext_character_map: &[Vec<u8>]) -> Vec<usize> {
     any(feature = "backend-openssl", feature = "backend-rust-crypto")
 ))]
 mod tests {
-    use super::{get_message_count, search, PowSearchParameters};
-    use crate::{Hash as _, SearchResult};
+    use super::{
+        search, search_by_hash_type, search_with_hasher, HashOutput, PowSearchParameters,
+        WorkDistribution,
+    };
+    use crate::{
+        CancellationToken, Hash as _, HashError, HashType, PowError, SearchOutcome, SearchReport,
+        Solution, ThreadConfig, Tokens,
+    };
 
     #[test]
     #[cfg(feature = "backend-openssl")]
@@ -332,11 +817,26 @@ mod tests {
             target_hash_mask: *md5_mask,
             plaintext_character_map: vec![vec![b'a', b'b', b'c', b'd', b'e']; 5],
         };
-        match search::<Md5>(search_params, Some(2)) {
-            SearchResult::Found(found) => {
+        match search::<Md5>(
+            search_params,
+            &crate::ThreadConfig {
+                thread_count: Some(2),
+                cancel: None,
+                deterministic: false,
+                progress: None,
+                priority: 0,
+            },
+        ) {
+            Ok(SearchReport {
+                outcome:
+                    SearchOutcome::Found(Solution {
+                        plaintext: found, ..
+                    }),
+                ..
+            }) => {
                 assert_eq!(found, vec![b'a', b'b', b'c', b'd', b'e']);
             }
-            SearchResult::NotFound => {
+            Ok(_) => {
                 panic!("Unexpected not found");
             }
             _ => {
@@ -353,13 +853,19 @@ mod tests {
             target_hash_mask: *md5_mask,
             plaintext_character_map: vec![vec![b'a', b'b', b'c', b'd', b'e']; 20],
         };
-        match search::<Md5>(search_params, None) {
-            SearchResult::Found(found) => {
-                let digest = Md5::hash(&found);
+        match search::<Md5>(search_params, &crate::ThreadConfig::default()) {
+            Ok(SearchReport {
+                outcome:
+                    SearchOutcome::Found(Solution {
+                        plaintext: found, ..
+                    }),
+                ..
+            }) => {
+                let digest = Md5::default().hash(&found);
                 assert_eq!(digest[0], 0);
                 assert_eq!(digest[1], 0);
             }
-            SearchResult::NotFound => {
+            Ok(_) => {
                 panic!("Unexpected not found");
             }
             _ => {
@@ -384,12 +890,31 @@ mod tests {
             &ThreadConfig {
                 thread_count: Some(2),
                 cancel: None,
+                deterministic: false,
+                progress: None,
+                priority: 0,
             },
         ) {
-            SearchResult::Found(found) => {
+            Ok(SearchReport {
+                outcome:
+                    SearchOutcome::Found(Solution {
+                        plaintext: found,
+                        digest,
+                        index,
+                    }),
+                total_hashes,
+                thread_hashes,
+                ..
+            }) => {
                 assert_eq!(found, vec![b'a', b'b', b'c', b'd', b'e']);
+                assert_eq!(&digest, md5_abcde);
+                // 01234 in base 5
+                assert_eq!(index, 194u32.into());
+                assert_eq!(thread_hashes.len(), 3);
+                assert_eq!(thread_hashes.iter().sum::<u64>(), total_hashes);
+                assert!(total_hashes > 0);
             }
-            SearchResult::NotFound => {
+            Ok(_) => {
                 panic!("Unexpected not found");
             }
             _ => {
@@ -411,14 +936,23 @@ mod tests {
             &ThreadConfig {
                 thread_count: Some(2),
                 cancel: None,
+                deterministic: false,
+                progress: None,
+                priority: 0,
             },
         ) {
-            SearchResult::Found(found) => {
-                let digest = Md5::hash(&found);
+            Ok(SearchReport {
+                outcome:
+                    SearchOutcome::Found(Solution {
+                        plaintext: found, ..
+                    }),
+                ..
+            }) => {
+                let digest = Md5::default().hash(&found);
                 assert_eq!(digest[0], 0);
                 assert_eq!(digest[1], 0);
             }
-            SearchResult::NotFound => {
+            Ok(_) => {
                 panic!("Unexpected not found");
             }
             _ => {
@@ -428,32 +962,338 @@ mod tests {
     }
 
     #[test]
-    fn test_get_message_count() {
+    fn test_search_errors() {
+        let thread_config = ThreadConfig::default();
+        let character_map = [b"ab".to_vec(), vec![], b"cd".to_vec()];
+        assert_eq!(
+            search_by_hash_type(
+                HashType::Md5,
+                &[0; 15],
+                &[0; 16],
+                &character_map,
+                &thread_config
+            ),
+            Err(PowError::TargetHashLength {
+                expected: 16,
+                actual: 15
+            })
+        );
+        assert_eq!(
+            search_by_hash_type(
+                HashType::Sha1,
+                &[0; 20],
+                &[0; 32],
+                &character_map,
+                &thread_config
+            ),
+            Err(PowError::TargetHashMaskLength {
+                expected: 20,
+                actual: 32
+            })
+        );
+        assert_eq!(
+            search_by_hash_type(
+                HashType::Sha1,
+                &[0; 20],
+                &[0; 20],
+                &character_map,
+                &thread_config
+            ),
+            Err(PowError::EmptyAlphabet { position: 1 })
+        );
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let cancel = CancellationToken::new();
+        let thread_config = ThreadConfig {
+            thread_count: Some(2),
+            cancel: Some(cancel.clone()),
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+        // A solution leaves the token untouched
+        let result = search_by_hash_type(
+            HashType::Md5,
+            &[0; 16],
+            &[0; 16],
+            &[b"ab".to_vec()],
+            &thread_config,
+        );
+        assert!(matches!(
+            result,
+            Ok(SearchReport {
+                outcome: SearchOutcome::Found(_),
+                ..
+            })
+        ));
+        assert!(!cancel.is_cancelled());
+
+        cancel.cancel();
+        let result = search_by_hash_type(
+            HashType::Md5,
+            &[0; 16],
+            &[0xff; 16],
+            &vec![(0..=255).collect::<Vec<u8>>(); 8],
+            &thread_config,
+        );
+        let report = result.unwrap();
+        assert_eq!(report.outcome, SearchOutcome::Cancelled);
+        assert_eq!(report.thread_hashes.len(), 3);
+        assert_eq!(report.into_solution(), Err(PowError::Cancelled));
+    }
+
+    #[derive(Default)]
+    struct PanickingHash;
+
+    impl crate::Hash for PanickingHash {
+        type Output = [u8; 1];
+        const PARALLEL_BLOCK_SIZE: usize = 16;
+
+        fn hash(&self, bytes: &[u8]) -> [u8; 1] {
+            if bytes == b"zz" {
+                panic!("bad input");
+            }
+            [0]
+        }
+    }
+
+    #[derive(Default)]
+    struct FailingHash;
+
+    impl crate::Hash for FailingHash {
+        type Output = [u8; 1];
+        const PARALLEL_BLOCK_SIZE: usize = 16;
+
+        fn hash(&self, _bytes: &[u8]) -> [u8; 1] {
+            unreachable!()
+        }
+
+        fn try_hash(&self, _bytes: &[u8]) -> Result<[u8; 1], HashError> {
+            Err(HashError::new("device lost"))
+        }
+    }
+
+    #[test]
+    fn test_failing_hash() {
+        let thread_config = ThreadConfig {
+            thread_count: Some(2),
+            cancel: None,
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+        let alphabet: Vec<u8> = (b'a'..=b'z').collect();
+        let result = search_with_hasher(
+            PanickingHash,
+            PowSearchParameters {
+                target_hash: [1],
+                target_hash_mask: [0xff],
+                plaintext_character_map: vec![alphabet.clone(); 2],
+            },
+            &thread_config,
+        );
         assert_eq!(
-            get_message_count(&vec![
-                vec![b'1', b'2', b'3', b'4'],
-                vec![b'1', b'2', b'3'],
-                vec![b'1', b'2'],
-                vec![b'1', b'2', b'3', b'4'],
-            ]),
-            vec![96, 24, 8, 4]
+            result,
+            Err(PowError::WorkerPanic {
+                message: "bad input".to_string()
+            })
+        );
+
+        let result = search_with_hasher(
+            FailingHash,
+            PowSearchParameters {
+                target_hash: [1],
+                target_hash_mask: [0xff],
+                plaintext_character_map: vec![alphabet; 2],
+            },
+            &thread_config,
         );
+        assert_eq!(result, Err(PowError::Hash(HashError::new("device lost"))));
+    }
+
+    /// FNV-1a with small work units, handed out in blocks or strides.
+    #[derive(Default, Clone)]
+    struct SmallUnits<const STRIDED: bool>;
+
+    impl<const STRIDED: bool> crate::Hash for SmallUnits<STRIDED> {
+        type Output = [u8; 8];
+        const PARALLEL_BLOCK_SIZE: usize = 4;
+        const WORK_DISTRIBUTION: WorkDistribution = if STRIDED {
+            WorkDistribution::SingleCandidate
+        } else {
+            WorkDistribution::Block
+        };
+
+        fn hash(&self, bytes: &[u8]) -> [u8; 8] {
+            crate::Fnv1a64::default().hash(bytes)
+        }
+    }
+
+    fn check_deterministic<H: crate::Hash + Default + Clone>(
+        plaintext_character_map: Vec<Vec<u8>>,
+    ) {
+        // The low byte of FNV-1a mixes best for short inputs
+        let mut target_hash_mask = H::Output::zero();
+        *target_hash_mask.as_slice_mut().last_mut().unwrap() = 0xff;
+        let params = PowSearchParameters::<H> {
+            target_hash: H::Output::zero(),
+            target_hash_mask,
+            plaintext_character_map,
+        };
+        // The first hit in lexicographic order
+        let mut expected = None;
+        let mut plaintext = vec![0; params.plaintext_character_map.len()];
+        let mut indices = vec![0; plaintext.len()];
+        'outer: loop {
+            for (c, (alphabet, &i)) in plaintext
+                .iter_mut()
+                .zip(params.plaintext_character_map.iter().zip(&indices))
+            {
+                *c = alphabet[i];
+            }
+            if params.check_hash(&H::default().hash(&plaintext)) {
+                expected = Some(plaintext.clone());
+                break;
+            }
+            for pos in (0..indices.len()).rev() {
+                indices[pos] += 1;
+                if indices[pos] < params.plaintext_character_map[pos].len() {
+                    continue 'outer;
+                }
+                indices[pos] = 0;
+            }
+            break;
+        }
+        assert!(expected.is_some());
+
+        let thread_config = ThreadConfig {
+            thread_count: Some(4),
+            cancel: None,
+            deterministic: true,
+            progress: None,
+            priority: 0,
+        };
+        for _ in 0..5 {
+            let report = search(params.clone(), &thread_config).unwrap();
+            assert_eq!(report.solution().map(|s| &s.plaintext), expected.as_ref());
+        }
+    }
+
+    #[test]
+    fn test_deterministic() {
+        // Units start at four candidates and grow
+        check_deterministic::<SmallUnits<false>>(vec![b"abcd".to_vec(); 6]);
+        check_deterministic::<SmallUnits<false>>(vec![b"abcdefgh".to_vec(); 4]);
+        check_deterministic::<SmallUnits<true>>(vec![b"abcd".to_vec(); 6]);
+
+        // CRC searches skip the linear solve, whose solution is not the first
+        let map = vec![(b'a'..=b'z').collect::<Vec<u8>>(); 6];
+        check_deterministic::<crate::Crc32>(map.clone());
+        let thread_config = ThreadConfig {
+            thread_count: Some(4),
+            deterministic: true,
+            ..ThreadConfig::default()
+        };
+        let expected = search::<crate::Crc32>(
+            PowSearchParameters {
+                target_hash: [0; 4],
+                target_hash_mask: [0, 0, 0, 0xff],
+                plaintext_character_map: map.clone(),
+            },
+            &thread_config,
+        )
+        .unwrap();
+        let report = search_by_hash_type(
+            HashType::Crc32,
+            &[0; 4],
+            &[0, 0, 0, 0xff],
+            &map,
+            &thread_config,
+        )
+        .unwrap();
+        assert_eq!(
+            report.solution().unwrap().plaintext,
+            expected.solution().unwrap().plaintext
+        );
+    }
+
+    #[test]
+    fn test_keyspace_edges() {
+        let thread_config = ThreadConfig {
+            thread_count: Some(4),
+            ..ThreadConfig::default()
+        };
+        // A single candidate, the empty plaintext
+        let report = search_by_hash_type(
+            HashType::Md5,
+            &[0; 16],
+            &[0; 16],
+            &[] as &[Vec<u8>],
+            &thread_config,
+        )
+        .unwrap();
+        assert_eq!(report.solution().unwrap().plaintext, b"");
+        assert_eq!(report.total_hashes, 1);
+        // Fewer candidates than workers, none of them a match
+        let report = search_by_hash_type(
+            HashType::Md5,
+            &[0; 16],
+            &[0xff; 16],
+            &[b"ab".to_vec()],
+            &thread_config,
+        )
+        .unwrap();
+        assert_eq!(report.outcome, SearchOutcome::NotFound);
+        assert_eq!(report.total_hashes, 2);
+        // More candidates than fit in an index
+        let report = search_by_hash_type(
+            HashType::Md5,
+            &[0; 16],
+            &[0xf0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            &vec![(0..=255).collect::<Vec<u8>>(); 32],
+            &thread_config,
+        )
+        .unwrap();
+        assert_eq!(report.solution().unwrap().digest[0] & 0xf0, 0);
+    }
+
+    #[test]
+    fn test_search_tokens() {
+        let thread_config = ThreadConfig {
+            thread_count: Some(4),
+            deterministic: true,
+            ..ThreadConfig::default()
+        };
+        let mut plaintext_character_map = vec![Tokens::new(["root", "admin", "ゲスト"])];
+        plaintext_character_map.extend(vec![Tokens::from_bytes("0123456789"); 4]);
+        let target = crate::Md5::default().hash(b"admin2024");
+        let report = search_by_hash_type(
+            HashType::Md5,
+            &target,
+            &[0xff; 16],
+            &plaintext_character_map,
+            &thread_config,
+        )
+        .unwrap();
+        let solution = report.solution().unwrap();
+        assert_eq!(solution.plaintext, b"admin2024");
+        assert_eq!(solution.index, 12024u32.into());
 
-        let v = vec![(0..=255).collect::<Vec<u8>>(); 10];
+        // CRCs fall back to enumeration when tokens are not single bytes
+        let target = crate::Crc32::default().hash("ゲスト0042".as_bytes());
+        let report = search_by_hash_type(
+            HashType::Crc32,
+            &target,
+            &[0xff; 4],
+            &plaintext_character_map,
+            &thread_config,
+        )
+        .unwrap();
         assert_eq!(
-            get_message_count(&v),
-            vec![
-                18446744073709551615,
-                18446744073709551615,
-                18446744073709551615,
-                72057594037927936,
-                281474976710656,
-                1099511627776,
-                4294967296,
-                16777216,
-                65536,
-                256
-            ]
+            report.solution().unwrap().plaintext,
+            "ゲスト0042".as_bytes()
         );
     }
 }
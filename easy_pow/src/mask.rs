@@ -0,0 +1,301 @@
+//! Hashcat mask syntax.
+//!
+//! A mask has one position per byte, where `?l`, `?u`, `?d`, `?h`, `?H`,
+//! `?s`, `?a` and `?b` stand for the built-in charsets of hashcat, `?1` to
+//! `?4` for custom charsets and `??` for a literal `?`. Custom charsets use
+//! the same syntax and may refer to the custom charsets before them.
+//!
+//! A `.hcmask` file has one mask per line, optionally preceded by up to four
+//! comma-separated custom charsets. `\,` is a literal comma, and lines
+//! starting with `#` are comments.
+
+use std::{io, path::Path, time::Instant};
+
+use thiserror::Error;
+
+use crate::{
+    search_by_hash_type, HashType, PlaintextTemplate, PowError, SearchOutcome, SearchReport,
+    ThreadConfig,
+};
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum MaskError {
+    #[error("the mask ends with a single `?`")]
+    TrailingQuestionMark,
+    #[error("unknown charset `?{0}`")]
+    UnknownCharset(char),
+    #[error("custom charset ?{0} is not defined")]
+    UndefinedCustomCharset(usize),
+    #[error("custom charset ?{0} is empty")]
+    EmptyCustomCharset(usize),
+    #[error("at most 4 custom charsets can be defined, got {0}")]
+    TooManyCustomCharsets(usize),
+}
+
+#[derive(Debug, Error)]
+pub enum HcmaskError {
+    #[error("failed to read the mask file: {0}")]
+    Io(#[from] io::Error),
+    #[error("line {line}: {error}")]
+    Mask {
+        line: usize,
+        #[source]
+        error: MaskError,
+    },
+}
+
+const LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &[u8] = b"0123456789";
+const SPECIAL: &[u8] = b" !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// Reads `mask` as a sequence of charsets, one per position.
+fn parse_charsets(mask: &[u8], custom: &[Vec<u8>]) -> Result<Vec<Vec<u8>>, MaskError> {
+    let mut positions = Vec::with_capacity(mask.len());
+    let mut bytes = mask.iter();
+    while let Some(&c) = bytes.next() {
+        if c != b'?' {
+            positions.push(vec![c]);
+            continue;
+        }
+        let name = *bytes.next().ok_or(MaskError::TrailingQuestionMark)?;
+        positions.push(match name {
+            b'l' => LOWER.to_vec(),
+            b'u' => UPPER.to_vec(),
+            b'd' => DIGITS.to_vec(),
+            b'h' => b"0123456789abcdef".to_vec(),
+            b'H' => b"0123456789ABCDEF".to_vec(),
+            b's' => SPECIAL.to_vec(),
+            b'a' => [LOWER, UPPER, DIGITS, SPECIAL].concat(),
+            b'b' => (0..=255).collect(),
+            b'?' => vec![b'?'],
+            b'1'..=b'4' => {
+                let n = (name - b'0') as usize;
+                custom
+                    .get(n - 1)
+                    .ok_or(MaskError::UndefinedCustomCharset(n))?
+                    .clone()
+            }
+            _ => return Err(MaskError::UnknownCharset(name as char)),
+        });
+    }
+    Ok(positions)
+}
+
+/// Expands custom charset definitions, each of which may refer to the ones
+/// before it.
+fn parse_custom_charsets(definitions: &[&[u8]]) -> Result<Vec<Vec<u8>>, MaskError> {
+    if definitions.len() > 4 {
+        return Err(MaskError::TooManyCustomCharsets(definitions.len()));
+    }
+    let mut custom = Vec::with_capacity(definitions.len());
+    for (i, definition) in definitions.iter().enumerate() {
+        let mut charset = Vec::new();
+        for c in parse_charsets(definition, &custom)?.concat() {
+            if !charset.contains(&c) {
+                charset.push(c);
+            }
+        }
+        if charset.is_empty() {
+            return Err(MaskError::EmptyCustomCharset(i + 1));
+        }
+        custom.push(charset);
+    }
+    Ok(custom)
+}
+
+impl PlaintextTemplate {
+    /// Parses a hashcat mask such as `?u?l?l?d?d`, where `?1` to `?4` stand
+    /// for `custom_charsets` in order.
+    pub fn from_mask(mask: impl AsRef<[u8]>, custom_charsets: &[&[u8]]) -> Result<Self, MaskError> {
+        let custom = parse_custom_charsets(custom_charsets)?;
+        let template = parse_charsets(mask.as_ref(), &custom)?
+            .into_iter()
+            .fold(PlaintextTemplate::new(), |template, chars| {
+                template.variable(chars, 1)
+            });
+        Ok(template)
+    }
+}
+
+/// Splits a `.hcmask` line at commas that are not escaped.
+fn split_hcmask_line(line: &str) -> Vec<Vec<u8>> {
+    let mut fields = vec![Vec::new()];
+    let mut bytes = line.bytes().peekable();
+    while let Some(c) = bytes.next() {
+        match c {
+            b'\\' if bytes.peek() == Some(&b',') => {
+                bytes.next();
+                fields.last_mut().unwrap().push(b',');
+            }
+            b',' => fields.push(Vec::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// Parses the contents of a `.hcmask` file into one template per mask line.
+pub fn parse_hcmask(contents: &str) -> Result<Vec<PlaintextTemplate>, HcmaskError> {
+    let mut templates = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = split_hcmask_line(line);
+        let (mask, custom_charsets) = fields.split_last().expect("split yields a field");
+        let custom_charsets: Vec<&[u8]> = custom_charsets.iter().map(Vec::as_slice).collect();
+        let template = PlaintextTemplate::from_mask(mask, &custom_charsets)
+            .map_err(|error| HcmaskError::Mask { line: i + 1, error })?;
+        templates.push(template);
+    }
+    Ok(templates)
+}
+
+/// Reads a `.hcmask` file. See [`parse_hcmask`].
+pub fn read_hcmask(path: impl AsRef<Path>) -> Result<Vec<PlaintextTemplate>, HcmaskError> {
+    parse_hcmask(&std::fs::read_to_string(path)?)
+}
+
+/// Runs [`search_by_hash_type`] on each template in turn, like hashcat does
+/// with the lines of a `.hcmask` file, until one finds a solution or the
+/// search is cancelled.
+///
+/// The report adds up the statistics of every run.
+pub fn search_masks_by_hash_type(
+    hash_type: HashType,
+    target_hash: &[u8],
+    target_hash_mask: &[u8],
+    templates: &[PlaintextTemplate],
+    thread_config: &ThreadConfig,
+) -> Result<SearchReport<Vec<u8>>, PowError> {
+    let start = Instant::now();
+    let mut thread_hashes: Vec<u64> = Vec::new();
+    let mut outcome = SearchOutcome::NotFound;
+    for template in templates {
+        let report = search_by_hash_type(
+            hash_type.clone(),
+            target_hash,
+            target_hash_mask,
+            template.plaintext_character_map(),
+            thread_config,
+        )?;
+        if thread_hashes.len() < report.thread_hashes.len() {
+            thread_hashes.resize(report.thread_hashes.len(), 0);
+        }
+        for (total, hashes) in thread_hashes.iter_mut().zip(&report.thread_hashes) {
+            *total += hashes;
+        }
+        outcome = report.outcome;
+        if !matches!(outcome, SearchOutcome::NotFound) {
+            break;
+        }
+    }
+    Ok(SearchReport::new(outcome, thread_hashes, start.elapsed()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_hcmask, HcmaskError, MaskError};
+    use crate::PlaintextTemplate;
+
+    fn map(mask: &str, custom_charsets: &[&[u8]]) -> Result<Vec<Vec<u8>>, MaskError> {
+        PlaintextTemplate::from_mask(mask, custom_charsets).map(PlaintextTemplate::build)
+    }
+
+    #[test]
+    fn test_mask() {
+        let positions = map("a?d??b?l?u?h?H?s", &[]).unwrap();
+        assert_eq!(positions.len(), 9);
+        assert_eq!(positions[0], b"a");
+        assert_eq!(positions[1], b"0123456789");
+        assert_eq!(positions[2], b"?");
+        assert_eq!(positions[3], b"b");
+        assert_eq!(positions[4].len(), 26);
+        assert_eq!(positions[5][0], b'A');
+        assert_eq!(positions[6], b"0123456789abcdef");
+        assert_eq!(positions[7], b"0123456789ABCDEF");
+        assert_eq!(positions[8].len(), 33);
+        assert_eq!(map("?a?b", &[]).unwrap()[0].len(), 95);
+        assert_eq!(map("?a?b", &[]).unwrap()[1].len(), 256);
+
+        let positions = map("?1?2?2", &[b"?l?d", b"?1X?d"]).unwrap();
+        assert_eq!(positions[0].len(), 36);
+        // Duplicates from the reference to ?1 are dropped
+        assert_eq!(positions[1].len(), 37);
+        assert_eq!(positions[1][36], b'X');
+
+        assert_eq!(map("ab?", &[]), Err(MaskError::TrailingQuestionMark));
+        assert_eq!(map("?x", &[]), Err(MaskError::UnknownCharset('x')));
+        assert_eq!(
+            map("?2", &[b"ab"]),
+            Err(MaskError::UndefinedCustomCharset(2))
+        );
+        assert_eq!(
+            map("?1", &[b"?2", b"a"]),
+            Err(MaskError::UndefinedCustomCharset(2))
+        );
+        assert_eq!(map("?1", &[b""]), Err(MaskError::EmptyCustomCharset(1)));
+        assert_eq!(
+            map("a", &[b"a", b"b", b"c", b"d", b"e"]),
+            Err(MaskError::TooManyCustomCharsets(5))
+        );
+    }
+
+    #[test]
+    fn test_hcmask() {
+        let contents = "# comment\n\n?d?d\r\n?l?d,\\,x,?1?2\n";
+        let templates = parse_hcmask(contents).unwrap();
+        assert_eq!(templates.len(), 2);
+        assert_eq!(templates[0].len(), 2);
+        let positions = templates[1].plaintext_character_map();
+        assert_eq!(positions[0].len(), 36);
+        assert_eq!(positions[1], b",x");
+
+        match parse_hcmask("?d\n?9") {
+            Err(HcmaskError::Mask { line, error }) => {
+                assert_eq!(line, 2);
+                assert_eq!(error, MaskError::UnknownCharset('9'));
+            }
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    #[cfg(any(feature = "backend-openssl", feature = "backend-rust-crypto"))]
+    fn test_search_masks() {
+        use super::search_masks_by_hash_type;
+        use crate::{Hash, HashType, Md5, SearchOutcome, ThreadConfig};
+
+        let target = Md5::default().hash(b"ab7");
+        let templates = parse_hcmask("?d?d?d\n?l?l\nab?d\n?l?l?d\n").unwrap();
+        let thread_config = ThreadConfig {
+            thread_count: Some(2),
+            ..ThreadConfig::default()
+        };
+        let report = search_masks_by_hash_type(
+            HashType::Md5,
+            &target,
+            &[0xff; 16],
+            &templates,
+            &thread_config,
+        )
+        .unwrap();
+        assert_eq!(report.solution().unwrap().plaintext, b"ab7");
+        assert_eq!(report.thread_hashes.len(), 3);
+        // Every candidate of the first two lines, then the third up to the hit
+        assert!(report.total_hashes >= 1000 + 676 + 8);
+        assert!(report.total_hashes <= 1000 + 676 + 10);
+
+        let report = search_masks_by_hash_type(
+            HashType::Md5,
+            &target,
+            &[0xff; 16],
+            &templates[..2],
+            &thread_config,
+        )
+        .unwrap();
+        assert_eq!(report.outcome, SearchOutcome::NotFound);
+    }
+}
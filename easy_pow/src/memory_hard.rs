@@ -0,0 +1,196 @@
+use thiserror::Error;
+
+use crate::{Hash, WorkDistribution};
+
+#[derive(Debug, Error)]
+pub enum InvalidCostParametersError {
+    #[error("invalid scrypt parameters")]
+    Scrypt,
+    #[error("invalid argon2 parameters: {0}")]
+    Argon2(argon2::Error),
+    #[error("bcrypt cost must be between 4 and 31")]
+    BcryptCost,
+}
+
+/// scrypt with a fixed salt and a 32-byte output.
+#[derive(Debug, Clone)]
+pub struct Scrypt {
+    params: scrypt::Params,
+    salt: Vec<u8>,
+}
+
+impl Scrypt {
+    /// `log_n` is the base-2 logarithm of the CPU/memory cost `N`.
+    pub fn new(log_n: u8, r: u32, p: u32, salt: &[u8]) -> Result<Self, InvalidCostParametersError> {
+        let params =
+            scrypt::Params::new(log_n, r, p, 32).map_err(|_| InvalidCostParametersError::Scrypt)?;
+        Ok(Self {
+            params,
+            salt: salt.to_vec(),
+        })
+    }
+}
+
+impl Hash for Scrypt {
+    type Output = [u8; 32];
+    const PARALLEL_BLOCK_SIZE: usize = 1;
+    const WORK_DISTRIBUTION: WorkDistribution = WorkDistribution::SingleCandidate;
+
+    fn hash(&self, bytes: &[u8]) -> [u8; 32] {
+        let mut ret = [0u8; 32];
+        scrypt::scrypt(bytes, &self.salt, &self.params, &mut ret).expect("output length is valid");
+        ret
+    }
+}
+
+/// Argon2id (version 0x13) with a fixed salt and a 32-byte output.
+#[derive(Debug, Clone)]
+pub struct Argon2id {
+    argon2: argon2::Argon2<'static>,
+    salt: Vec<u8>,
+}
+
+impl Argon2id {
+    /// `m_cost` is the memory size in KiB, `t_cost` the number of passes and
+    /// `p_cost` the degree of parallelism. The salt must be at least 8 bytes.
+    pub fn new(
+        m_cost: u32,
+        t_cost: u32,
+        p_cost: u32,
+        salt: &[u8],
+    ) -> Result<Self, InvalidCostParametersError> {
+        let params = argon2::Params::new(m_cost, t_cost, p_cost, Some(32))
+            .map_err(InvalidCostParametersError::Argon2)?;
+        if salt.len() < argon2::MIN_SALT_LEN {
+            return Err(InvalidCostParametersError::Argon2(
+                argon2::Error::SaltTooShort,
+            ));
+        }
+        Ok(Self {
+            argon2: argon2::Argon2::new(
+                argon2::Algorithm::Argon2id,
+                argon2::Version::V0x13,
+                params,
+            ),
+            salt: salt.to_vec(),
+        })
+    }
+}
+
+impl Hash for Argon2id {
+    type Output = [u8; 32];
+    const PARALLEL_BLOCK_SIZE: usize = 1;
+    const WORK_DISTRIBUTION: WorkDistribution = WorkDistribution::SingleCandidate;
+
+    fn hash(&self, bytes: &[u8]) -> [u8; 32] {
+        let mut ret = [0u8; 32];
+        self.argon2
+            .hash_password_into(bytes, &self.salt, &mut ret)
+            .expect("parameters are checked in Argon2id::new");
+        ret
+    }
+}
+
+/// bcrypt with a fixed salt.
+///
+/// As in the OpenBSD implementation, a NUL terminator is appended to the input
+/// and the result is truncated to 72 bytes. The output is the 23 bytes that
+/// are encoded in a `$2b$` hash string.
+#[derive(Debug, Clone)]
+pub struct Bcrypt {
+    cost: u32,
+    salt: [u8; 16],
+}
+
+impl Bcrypt {
+    pub fn new(cost: u32, salt: [u8; 16]) -> Result<Self, InvalidCostParametersError> {
+        if !(4..=31).contains(&cost) {
+            return Err(InvalidCostParametersError::BcryptCost);
+        }
+        Ok(Self { cost, salt })
+    }
+}
+
+impl Hash for Bcrypt {
+    type Output = [u8; 23];
+    const PARALLEL_BLOCK_SIZE: usize = 1;
+    const WORK_DISTRIBUTION: WorkDistribution = WorkDistribution::SingleCandidate;
+
+    fn hash(&self, bytes: &[u8]) -> [u8; 23] {
+        let mut password = [0u8; 72];
+        let len = bytes.len().min(71);
+        password[..len].copy_from_slice(&bytes[..len]);
+        let len = (bytes.len() + 1).min(72);
+        let out = bcrypt::bcrypt(self.cost, self.salt, &password[..len]);
+        let mut ret = [0u8; 23];
+        ret.copy_from_slice(&out[..23]);
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Argon2id, Bcrypt, Scrypt};
+    use crate::{search_with_hasher, Hash, PowSearchParameters, ThreadConfig};
+
+    #[test]
+    fn test_scrypt_rfc7914() {
+        let scrypt = Scrypt::new(4, 1, 1, b"").unwrap();
+        assert_eq!(
+            scrypt.hash(b""),
+            *b"\x77\xd6\x57\x62\x38\x65\x7b\x20\x3b\x19\xca\x42\xc1\x8a\x04\x97\
+               \xf1\x6b\x48\x44\xe3\x07\x4a\xe8\xdf\xdf\xfa\x3f\xed\xe2\x14\x42"
+        );
+    }
+
+    #[test]
+    fn test_bcrypt_matches_hash_string() {
+        const ALPHABET: &[u8] = b"./ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+        let salt = *b"0123456789abcdef";
+        let hasher = Bcrypt::new(4, salt).unwrap();
+        let hash_string = bcrypt::hash_with_salt(b"easy_pow", 4, salt)
+            .unwrap()
+            .to_string();
+
+        // The last 31 characters are the digest in bcrypt's base64 alphabet
+        let mut bits = 0u32;
+        let mut bit_count = 0;
+        let mut expected = Vec::new();
+        for c in hash_string[hash_string.len() - 31..].bytes() {
+            bits = bits << 6 | ALPHABET.iter().position(|&a| a == c).unwrap() as u32;
+            bit_count += 6;
+            if bit_count >= 8 {
+                bit_count -= 8;
+                expected.push((bits >> bit_count) as u8);
+            }
+        }
+        assert_eq!(hasher.hash(b"easy_pow").to_vec(), expected);
+        assert!(Bcrypt::new(3, salt).is_err());
+    }
+
+    #[test]
+    fn test_search_argon2id() {
+        let hasher = Argon2id::new(8, 1, 1, b"easy_pow salt").unwrap();
+        let mut target_hash_mask = [0u8; 32];
+        target_hash_mask[0] = 0xf0;
+        let params = PowSearchParameters {
+            target_hash: [0u8; 32],
+            target_hash_mask,
+            plaintext_character_map: vec![(b'a'..=b'z').collect::<Vec<u8>>(); 3],
+        };
+        let thread_config = ThreadConfig {
+            thread_count: Some(3),
+            cancel: None,
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+        match search_with_hasher(hasher.clone(), params, &thread_config) {
+            Ok(report) => match report.solution() {
+                Some(solution) => assert_eq!(hasher.hash(&solution.plaintext)[0] & 0xf0, 0),
+                None => panic!("Unexpected not found"),
+            },
+            _ => panic!("Unexpected result"),
+        }
+    }
+}
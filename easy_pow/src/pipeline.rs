@@ -0,0 +1,617 @@
+use std::{cell::RefCell, fmt, marker::PhantomData, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{
+    Adler32, Crc32, Crc64, Fnv1a32, Fnv1a64, Hash, HashError, HashOutput, HashType, Md5, Murmur3,
+    Sha1, Sha224, Sha256, Sha384, Sha512, XxHash32, XxHash64,
+};
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ParsePipelineError {
+    #[error("unexpected end of expression")]
+    UnexpectedEnd,
+    #[error("unexpected character {0:?} at {1}")]
+    UnexpectedCharacter(char, usize),
+    #[error("unknown function {0:?}")]
+    UnknownFunction(String),
+    #[error("invalid literal at {0}")]
+    InvalidLiteral(usize),
+    #[error("the outermost stage of a pipeline must be a hash function")]
+    NotADigest,
+    #[error("the pipeline does not use the input `x`")]
+    MissingInput,
+    #[error("functions are nested more than {MAX_NESTING} levels deep")]
+    TooDeep,
+}
+
+/// How deeply function calls may be nested, so parsing cannot overflow the
+/// stack.
+const MAX_NESTING: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Algorithm {
+    Md5,
+    Sha1,
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+    Crc32,
+    Crc64,
+    Adler32,
+    XxHash32,
+    XxHash64,
+    Fnv1a32,
+    Fnv1a64,
+    Murmur3,
+}
+
+impl Algorithm {
+    /// The algorithm of a lowercase hash name, shared with [`HashType`]'s
+    /// `FromStr`.
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "md5" => Some(Self::Md5),
+            "sha1" => Some(Self::Sha1),
+            "sha224" => Some(Self::Sha224),
+            "sha256" => Some(Self::Sha256),
+            "sha384" => Some(Self::Sha384),
+            "sha512" => Some(Self::Sha512),
+            "crc32" => Some(Self::Crc32),
+            "crc64" => Some(Self::Crc64),
+            "adler32" => Some(Self::Adler32),
+            "xxh32" | "xxhash32" => Some(Self::XxHash32),
+            "xxh64" | "xxhash64" => Some(Self::XxHash64),
+            "fnv1a32" => Some(Self::Fnv1a32),
+            "fnv1a64" => Some(Self::Fnv1a64),
+            "murmur3" => Some(Self::Murmur3),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+            Self::Sha224 => "sha224",
+            Self::Sha256 => "sha256",
+            Self::Sha384 => "sha384",
+            Self::Sha512 => "sha512",
+            Self::Crc32 => "crc32",
+            Self::Crc64 => "crc64",
+            Self::Adler32 => "adler32",
+            Self::XxHash32 => "xxh32",
+            Self::XxHash64 => "xxh64",
+            Self::Fnv1a32 => "fnv1a32",
+            Self::Fnv1a64 => "fnv1a64",
+            Self::Murmur3 => "murmur3",
+        }
+    }
+
+    fn output_len(self) -> usize {
+        match self {
+            Self::Md5 => 16,
+            Self::Sha1 => 20,
+            Self::Sha224 => 28,
+            Self::Sha256 => 32,
+            Self::Sha384 => 48,
+            Self::Sha512 => 64,
+            Self::Crc32 | Self::Adler32 | Self::XxHash32 | Self::Fnv1a32 | Self::Murmur3 => 4,
+            Self::Crc64 | Self::XxHash64 | Self::Fnv1a64 => 8,
+        }
+    }
+
+    /// Appends the digest of `bytes`, failing like the backend's
+    /// [`Hash::try_hash`].
+    fn digest(self, bytes: &[u8], out: &mut Vec<u8>) -> Result<(), HashError> {
+        match self {
+            Self::Md5 => out.extend_from_slice(&Md5::default().try_hash(bytes)?),
+            Self::Sha1 => out.extend_from_slice(&Sha1::default().try_hash(bytes)?),
+            Self::Sha224 => out.extend_from_slice(&Sha224::default().try_hash(bytes)?),
+            Self::Sha256 => out.extend_from_slice(&Sha256::default().try_hash(bytes)?),
+            Self::Sha384 => out.extend_from_slice(&Sha384::default().try_hash(bytes)?),
+            Self::Sha512 => out.extend_from_slice(&Sha512::default().try_hash(bytes)?),
+            Self::Crc32 => out.extend_from_slice(&Crc32::default().try_hash(bytes)?),
+            Self::Crc64 => out.extend_from_slice(&Crc64::default().try_hash(bytes)?),
+            Self::Adler32 => out.extend_from_slice(&Adler32::default().try_hash(bytes)?),
+            Self::XxHash32 => out.extend_from_slice(&XxHash32::default().try_hash(bytes)?),
+            Self::XxHash64 => out.extend_from_slice(&XxHash64::default().try_hash(bytes)?),
+            Self::Fnv1a32 => out.extend_from_slice(&Fnv1a32::default().try_hash(bytes)?),
+            Self::Fnv1a64 => out.extend_from_slice(&Fnv1a64::default().try_hash(bytes)?),
+            Self::Murmur3 => out.extend_from_slice(&Murmur3::default().try_hash(bytes)?),
+        }
+        Ok(())
+    }
+
+    pub(crate) fn hash_type(self) -> HashType {
+        match self {
+            Self::Md5 => HashType::Md5,
+            Self::Sha1 => HashType::Sha1,
+            Self::Sha224 => HashType::Sha224,
+            Self::Sha256 => HashType::Sha256,
+            Self::Sha384 => HashType::Sha384,
+            Self::Sha512 => HashType::Sha512,
+            Self::Crc32 => HashType::Crc32,
+            Self::Crc64 => HashType::Crc64,
+            Self::Adler32 => HashType::Adler32,
+            Self::XxHash32 => HashType::XxHash32,
+            Self::XxHash64 => HashType::XxHash64,
+            Self::Fnv1a32 => HashType::Fnv1a32,
+            Self::Fnv1a64 => HashType::Fnv1a64,
+            Self::Murmur3 => HashType::Murmur3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Input,
+    Literal(Vec<u8>),
+    Digest(Algorithm, Box<Node>),
+    Hex(Box<Node>),
+    Base64(Box<Node>),
+    Concat(Vec<Node>),
+}
+
+/// A composition of hash functions, encodings and constants, such as
+/// `sha256(hex(md5(x)) || "salt")`.
+///
+/// The grammar is:
+///
+/// ```text
+/// expr  := term ("||" term)*
+/// term  := "x" | "input" | literal | func "(" expr ")"
+/// func  := hash-name | "hex" | "base64"
+/// literal := "\"...\"" | "'...'" | "0x" hex-digits
+/// ```
+///
+/// `hash-name` is any name accepted by [`HashType`] (e.g. `sha256` or
+/// `crc32`). `x` is the candidate plaintext. `hex` produces lowercase hex and `base64`
+/// uses the standard alphabet with padding. String literals accept `\\`, `\"`,
+/// `\'`, `\n`, `\r`, `\t`, `\0` and `\xNN` escapes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pipeline {
+    root: Node,
+}
+
+impl Pipeline {
+    /// The length in bytes of the digest produced by the outermost stage.
+    pub fn output_len(&self) -> usize {
+        match &self.root {
+            Node::Digest(algorithm, _) => algorithm.output_len(),
+            _ => unreachable!("the outermost stage is always a digest"),
+        }
+    }
+
+    /// Evaluates the pipeline for `input` and appends the result to `out`.
+    /// Fails if the backend of a hash stage does.
+    pub fn evaluate(&self, input: &[u8], out: &mut Vec<u8>) -> Result<(), HashError> {
+        evaluate(&self.root, input, out, &mut Vec::new())
+    }
+
+    /// Returns the plain hash type if the pipeline is just `<hash>(x)`.
+    pub(crate) fn as_hash_type(&self) -> Option<HashType> {
+        match &self.root {
+            Node::Digest(algorithm, inner) if **inner == Node::Input => Some(algorithm.hash_type()),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = ParsePipelineError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            input: s.as_bytes(),
+            pos: 0,
+            depth: 0,
+        };
+        let root = parser.parse_expr()?;
+        parser.skip_whitespace();
+        if let Some(c) = parser.peek() {
+            return Err(ParsePipelineError::UnexpectedCharacter(
+                c as char, parser.pos,
+            ));
+        }
+        if !matches!(root, Node::Digest(..)) {
+            return Err(ParsePipelineError::NotADigest);
+        }
+        if !uses_input(&root) {
+            return Err(ParsePipelineError::MissingInput);
+        }
+        Ok(Pipeline { root })
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_node(&self.root, f)
+    }
+}
+
+/// A [`Pipeline`] with a statically known output type, usable with
+/// [`search_with_hasher`](crate::search_with_hasher).
+#[derive(Debug, Clone)]
+pub struct PipelineHash<O> {
+    pipeline: Pipeline,
+    _output: PhantomData<fn() -> O>,
+}
+
+impl<O: HashOutput> PipelineHash<O> {
+    /// Returns `None` if the output length of `pipeline` differs from `O`.
+    pub fn new(pipeline: Pipeline) -> Option<Self> {
+        if pipeline.output_len() != O::HASH_BYTES {
+            return None;
+        }
+        Some(Self {
+            pipeline,
+            _output: PhantomData,
+        })
+    }
+
+    pub fn pipeline(&self) -> &Pipeline {
+        &self.pipeline
+    }
+}
+
+impl<O: HashOutput> Hash for PipelineHash<O> {
+    type Output = O;
+    const PARALLEL_BLOCK_SIZE: usize = 4096;
+
+    fn hash(&self, bytes: &[u8]) -> O {
+        self.try_hash(bytes).expect("failed to hash")
+    }
+
+    fn try_hash(&self, bytes: &[u8]) -> Result<O, HashError> {
+        SCRATCH.with(|scratch| {
+            let scratch = &mut *scratch.borrow_mut();
+            let mut out = scratch.pop().unwrap_or_default();
+            out.clear();
+            let evaluated = evaluate(&self.pipeline.root, bytes, &mut out, scratch);
+            let output = evaluated.map(|()| {
+                O::from_slice(&out).expect("output length is checked in PipelineHash::new")
+            });
+            scratch.push(out);
+            output
+        })
+    }
+}
+
+thread_local! {
+    /// Buffers for the intermediate results of [`PipelineHash::hash`], kept
+    /// between candidates.
+    static SCRATCH: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Appends the result of `node` to `out`, taking buffers for the results of
+/// inner nodes from `scratch` and returning them there.
+fn evaluate(
+    node: &Node,
+    input: &[u8],
+    out: &mut Vec<u8>,
+    scratch: &mut Vec<Vec<u8>>,
+) -> Result<(), HashError> {
+    // Runs `stage` on the result of `inner`, returning the buffer even if
+    // either fails
+    let mut with_inner = |inner: &Node,
+                          stage: &mut dyn FnMut(&[u8]) -> Result<(), HashError>|
+     -> Result<(), HashError> {
+        let mut buf = scratch.pop().unwrap_or_default();
+        buf.clear();
+        let result = evaluate(inner, input, &mut buf, scratch).and_then(|()| stage(&buf));
+        scratch.push(buf);
+        result
+    };
+    match node {
+        Node::Input => out.extend_from_slice(input),
+        Node::Literal(bytes) => out.extend_from_slice(bytes),
+        Node::Digest(algorithm, inner) if **inner == Node::Input => algorithm.digest(input, out)?,
+        Node::Digest(algorithm, inner) => with_inner(inner, &mut |buf| algorithm.digest(buf, out))?,
+        Node::Hex(inner) => with_inner(inner, &mut |buf| {
+            crate::encoding::hex_encode(buf, out);
+            Ok(())
+        })?,
+        Node::Base64(inner) => with_inner(inner, &mut |buf| {
+            crate::encoding::base64_encode(buf, out);
+            Ok(())
+        })?,
+        Node::Concat(nodes) => {
+            for node in nodes {
+                evaluate(node, input, out, scratch)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn uses_input(node: &Node) -> bool {
+    match node {
+        Node::Input => true,
+        Node::Literal(_) => false,
+        Node::Digest(_, inner) | Node::Hex(inner) | Node::Base64(inner) => uses_input(inner),
+        Node::Concat(nodes) => nodes.iter().any(uses_input),
+    }
+}
+
+fn write_node(node: &Node, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match node {
+        Node::Input => write!(f, "x"),
+        Node::Literal(bytes) => {
+            write!(f, "\"")?;
+            for &b in bytes {
+                match b {
+                    b'"' => write!(f, "\\\"")?,
+                    b'\\' => write!(f, "\\\\")?,
+                    0x20..=0x7e => write!(f, "{}", b as char)?,
+                    _ => write!(f, "\\x{:02x}", b)?,
+                }
+            }
+            write!(f, "\"")
+        }
+        Node::Digest(algorithm, inner) => {
+            write!(f, "{}(", algorithm.name())?;
+            write_node(inner, f)?;
+            write!(f, ")")
+        }
+        Node::Hex(inner) => {
+            write!(f, "hex(")?;
+            write_node(inner, f)?;
+            write!(f, ")")
+        }
+        Node::Base64(inner) => {
+            write!(f, "base64(")?;
+            write_node(inner, f)?;
+            write!(f, ")")
+        }
+        Node::Concat(nodes) => {
+            for (i, node) in nodes.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " || ")?;
+                }
+                write_node(node, f)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    /// The number of enclosing function calls.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), ParsePipelineError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(d) if d == c => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(d) => Err(ParsePipelineError::UnexpectedCharacter(d as char, self.pos)),
+            None => Err(ParsePipelineError::UnexpectedEnd),
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Node, ParsePipelineError> {
+        let mut nodes = vec![self.parse_term()?];
+        loop {
+            self.skip_whitespace();
+            if self.input[self.pos..].starts_with(b"||") {
+                self.pos += 2;
+                nodes.push(self.parse_term()?);
+            } else {
+                break;
+            }
+        }
+        if nodes.len() == 1 {
+            Ok(nodes.pop().unwrap())
+        } else {
+            Ok(Node::Concat(nodes))
+        }
+    }
+
+    fn parse_term(&mut self) -> Result<Node, ParsePipelineError> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(ParsePipelineError::UnexpectedEnd),
+            Some(b'"') | Some(b'\'') => self.parse_string(),
+            Some(b'0') if self.input.get(self.pos + 1) == Some(&b'x') => self.parse_hex(),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.pos;
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == b'_') {
+                    self.pos += 1;
+                }
+                let name = std::str::from_utf8(&self.input[start..self.pos])
+                    .expect("identifier is ascii")
+                    .to_lowercase();
+                if name == "x" || name == "input" {
+                    return Ok(Node::Input);
+                }
+                self.expect(b'(')?;
+                if self.depth == MAX_NESTING {
+                    return Err(ParsePipelineError::TooDeep);
+                }
+                self.depth += 1;
+                let inner = Box::new(self.parse_expr()?);
+                self.depth -= 1;
+                self.expect(b')')?;
+                match name.as_str() {
+                    "hex" => Ok(Node::Hex(inner)),
+                    "base64" => Ok(Node::Base64(inner)),
+                    _ => match Algorithm::from_name(&name) {
+                        Some(algorithm) => Ok(Node::Digest(algorithm, inner)),
+                        None => Err(ParsePipelineError::UnknownFunction(name)),
+                    },
+                }
+            }
+            Some(c) => Err(ParsePipelineError::UnexpectedCharacter(c as char, self.pos)),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<Node, ParsePipelineError> {
+        let start = self.pos;
+        let quote = self.input[self.pos];
+        self.pos += 1;
+        let mut bytes = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(ParsePipelineError::UnexpectedEnd),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(Node::Literal(bytes));
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let escaped = match self.peek() {
+                        Some(b'n') => b'\n',
+                        Some(b'r') => b'\r',
+                        Some(b't') => b'\t',
+                        Some(b'0') => 0,
+                        Some(c @ (b'\\' | b'"' | b'\'')) => c,
+                        Some(b'x') => {
+                            let hex = self
+                                .input
+                                .get(self.pos + 1..self.pos + 3)
+                                .ok_or(ParsePipelineError::InvalidLiteral(start))?;
+                            let hex = std::str::from_utf8(hex)
+                                .map_err(|_| ParsePipelineError::InvalidLiteral(start))?;
+                            self.pos += 2;
+                            u8::from_str_radix(hex, 16)
+                                .map_err(|_| ParsePipelineError::InvalidLiteral(start))?
+                        }
+                        _ => return Err(ParsePipelineError::InvalidLiteral(start)),
+                    };
+                    self.pos += 1;
+                    bytes.push(escaped);
+                }
+                Some(c) => {
+                    self.pos += 1;
+                    bytes.push(c);
+                }
+            }
+        }
+    }
+
+    fn parse_hex(&mut self) -> Result<Node, ParsePipelineError> {
+        let start = self.pos;
+        self.pos += 2;
+        let digits_start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_hexdigit()) {
+            self.pos += 1;
+        }
+        let digits = &self.input[digits_start..self.pos];
+        if digits.is_empty() || !digits.len().is_multiple_of(2) {
+            return Err(ParsePipelineError::InvalidLiteral(start));
+        }
+        let bytes = digits
+            .chunks(2)
+            .map(|pair| {
+                u8::from_str_radix(std::str::from_utf8(pair).expect("hex digits are ascii"), 16)
+                    .expect("hex digits are checked")
+            })
+            .collect();
+        Ok(Node::Literal(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParsePipelineError, Pipeline, PipelineHash};
+    use crate::{
+        search_with_hasher, Hash, HashType, Md5, PowSearchParameters, Sha1, Sha256, ThreadConfig,
+    };
+
+    fn hex(bytes: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        crate::encoding::hex_encode(bytes, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_evaluate_pipeline() {
+        let pipeline: Pipeline = "md5(hex(sha1(x)))".parse().unwrap();
+        let hash = PipelineHash::<[u8; 16]>::new(pipeline).unwrap();
+        assert_eq!(
+            hash.hash(b"abc"),
+            Md5::default().hash(&hex(&Sha1::default().hash(b"abc")))
+        );
+
+        let pipeline: Pipeline = "sha256(hex(md5(x)) || 'salt' || 0x00ff)".parse().unwrap();
+        let hash = PipelineHash::<[u8; 32]>::new(pipeline).unwrap();
+        let mut expected = hex(&Md5::default().hash(b"abc"));
+        expected.extend_from_slice(b"salt\x00\xff");
+        assert_eq!(hash.hash(b"abc"), Sha256::default().hash(&expected));
+        assert_eq!(hash.try_hash(b"abc"), Ok(hash.hash(b"abc")));
+
+        let pipeline: Pipeline = "sha256(sha256(x))".parse().unwrap();
+        assert!(PipelineHash::<[u8; 16]>::new(pipeline.clone()).is_none());
+        assert_eq!(pipeline.to_string().parse::<Pipeline>().unwrap(), pipeline);
+    }
+
+    #[test]
+    fn test_parse_pipeline_error() {
+        assert_eq!(
+            "hex(md5(x))".parse::<Pipeline>(),
+            Err(ParsePipelineError::NotADigest)
+        );
+        assert_eq!(
+            "md5('salt')".parse::<Pipeline>(),
+            Err(ParsePipelineError::MissingInput)
+        );
+        assert_eq!(
+            "sha3(x)".parse::<Pipeline>(),
+            Err(ParsePipelineError::UnknownFunction("sha3".to_string()))
+        );
+        assert_eq!(
+            "md5(x".parse::<Pipeline>(),
+            Err(ParsePipelineError::UnexpectedEnd)
+        );
+        assert_eq!("sha1(x)".parse::<HashType>().unwrap(), HashType::Sha1);
+        let nested = format!("{}x{}", "md5(".repeat(65), ")".repeat(65));
+        assert_eq!(nested.parse::<Pipeline>(), Err(ParsePipelineError::TooDeep));
+        let nested = format!("{}x{}", "md5(".repeat(64), ")".repeat(64));
+        assert!(nested.parse::<Pipeline>().is_ok());
+    }
+
+    #[test]
+    fn test_search_pipeline() {
+        let pipeline: Pipeline = "md5(sha1(x) || 'salt')".parse().unwrap();
+        let hasher = PipelineHash::<[u8; 16]>::new(pipeline).unwrap();
+        let mut target_hash_mask = [0u8; 16];
+        target_hash_mask[0] = 0xff;
+        let params = PowSearchParameters {
+            target_hash: [0u8; 16],
+            target_hash_mask,
+            plaintext_character_map: vec![(b'a'..=b'z').collect::<Vec<u8>>(); 4],
+        };
+        let thread_config = ThreadConfig {
+            thread_count: Some(2),
+            cancel: None,
+            deterministic: false,
+            progress: None,
+            priority: 0,
+        };
+        match search_with_hasher(hasher.clone(), params, &thread_config) {
+            Ok(report) => match report.solution() {
+                Some(solution) => assert_eq!(hasher.hash(&solution.plaintext)[0], 0),
+                None => panic!("Unexpected not found"),
+            },
+            _ => panic!("Unexpected result"),
+        }
+    }
+}
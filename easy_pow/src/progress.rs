@@ -0,0 +1,28 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/// A live count of the hashes computed by a running search.
+///
+/// Clones share the same counter. Threads add their hashes after each work
+/// unit, so the count lags behind by about a millisecond of hashing per
+/// thread.
+#[derive(Debug, Clone, Default)]
+pub struct SearchProgress {
+    hashes: Arc<AtomicU64>,
+}
+
+impl SearchProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn hashes(&self) -> u64 {
+        self.hashes.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn add(&self, hashes: u64) {
+        self.hashes.fetch_add(hashes, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use num_bigint::BigUint;
+
+use crate::{HashOutput, PowError};
+
+/// A plaintext whose hash matches the target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution<O> {
+    pub plaintext: Vec<u8>,
+    pub digest: O,
+    /// The position of `plaintext` in the [`Keyspace`] of
+    /// `plaintext_character_map`.
+    pub index: BigUint,
+}
+
+impl<O: HashOutput> Solution<O> {
+    pub(crate) fn new(plaintext: Vec<u8>, digest: O, index: BigUint) -> Self {
+        Self {
+            plaintext,
+            digest,
+            index,
+        }
+    }
+
+    /// The same solution with the digest as a byte vector.
+    pub fn into_bytes(self) -> Solution<Vec<u8>> {
+        Solution {
+            plaintext: self.plaintext,
+            digest: self.digest.as_slice().to_vec(),
+            index: self.index,
+        }
+    }
+}
+
+/// How a search ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchOutcome<O> {
+    Found(Solution<O>),
+    /// Every plaintext was hashed without a match.
+    NotFound,
+    /// [`ThreadConfig::cancel`](crate::ThreadConfig::cancel) was cancelled
+    /// before a match was found.
+    Cancelled,
+}
+
+/// The outcome of a search together with statistics about the work done.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchReport<O> {
+    pub outcome: SearchOutcome<O>,
+    pub total_hashes: u64,
+    /// Hashes computed by each thread. The first entry is the calling thread,
+    /// which only hashes for the linear solve of
+    /// [`search_affine`](crate::search_affine), followed by the workers.
+    pub thread_hashes: Vec<u64>,
+    pub elapsed: Duration,
+}
+
+impl<O> SearchReport<O> {
+    pub(crate) fn new(
+        outcome: SearchOutcome<O>,
+        thread_hashes: Vec<u64>,
+        elapsed: Duration,
+    ) -> Self {
+        Self {
+            outcome,
+            total_hashes: thread_hashes.iter().sum(),
+            thread_hashes,
+            elapsed,
+        }
+    }
+
+    /// Hashes per second over the whole search.
+    pub fn hash_rate(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.total_hashes as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    pub fn solution(&self) -> Option<&Solution<O>> {
+        match &self.outcome {
+            SearchOutcome::Found(solution) => Some(solution),
+            _ => None,
+        }
+    }
+
+    /// Drops the statistics, turning a cancelled search into
+    /// [`PowError::Cancelled`].
+    pub fn into_solution(self) -> Result<Option<Solution<O>>, PowError> {
+        match self.outcome {
+            SearchOutcome::Found(solution) => Ok(Some(solution)),
+            SearchOutcome::NotFound => Ok(None),
+            SearchOutcome::Cancelled => Err(PowError::Cancelled),
+        }
+    }
+}
+
+impl<O: HashOutput> SearchReport<O> {
+    /// The same report with the digest as a byte vector, for callers that
+    /// pick the hash function at runtime.
+    pub fn into_bytes(self) -> SearchReport<Vec<u8>> {
+        SearchReport {
+            outcome: match self.outcome {
+                SearchOutcome::Found(solution) => SearchOutcome::Found(solution.into_bytes()),
+                SearchOutcome::NotFound => SearchOutcome::NotFound,
+                SearchOutcome::Cancelled => SearchOutcome::Cancelled,
+            },
+            total_hashes: self.total_hashes,
+            thread_hashes: self.thread_hashes,
+            elapsed: self.elapsed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use num_bigint::BigUint;
+
+    use super::{SearchOutcome, SearchReport, Solution};
+    use crate::PowError;
+
+    #[test]
+    fn test_report() {
+        let solution = Solution::new(b"b7y".to_vec(), [0u8; 4], BigUint::from(35u32));
+
+        let report = SearchReport::new(
+            SearchOutcome::Found(solution.clone()),
+            vec![10, 20, 30],
+            Duration::from_millis(500),
+        );
+        assert_eq!(report.total_hashes, 60);
+        assert_eq!(report.hash_rate(), 120.0);
+        assert_eq!(report.solution(), Some(&solution));
+        assert_eq!(
+            report.into_bytes().into_solution(),
+            Ok(Some(solution.into_bytes()))
+        );
+
+        let report = SearchReport::<[u8; 4]>::new(SearchOutcome::Cancelled, vec![], Duration::ZERO);
+        assert_eq!(report.hash_rate(), 0.0);
+        assert_eq!(report.into_solution(), Err(PowError::Cancelled));
+    }
+}
@@ -0,0 +1,224 @@
+//! Word mangling rules in the spirit of hashcat's rule engine.
+//!
+//! A rule is a sequence of functions applied to a word in order, written
+//! like hashcat rules with optional spaces between functions:
+//!
+//! | Function | Meaning |
+//! |----------|---------|
+//! | `:`      | leave the word as it is |
+//! | `l`, `u` | lowercase or uppercase every letter |
+//! | `c`, `C` | capitalize, or lowercase the first letter and uppercase the rest |
+//! | `t`      | toggle the case of every letter |
+//! | `TN`     | toggle the case of the letter at position `N` |
+//! | `r`      | reverse the word |
+//! | `d`      | duplicate the word |
+//! | `$X`     | append `X` |
+//! | `^X`     | prepend `X` |
+//! | `sXY`    | replace every `X` with `Y`, as in leetspeak |
+//!
+//! Positions `N` are `0-9` then `A-Z` for 10 to 35. Functions on positions
+//! past the end of the word leave it unchanged.
+
+use std::{io, path::Path};
+
+use thiserror::Error;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum RuleError {
+    #[error("unknown rule function `{0}`")]
+    UnknownFunction(char),
+    #[error("rule function `{0}` is missing an argument")]
+    MissingArgument(char),
+    #[error("invalid position `{0}`")]
+    InvalidPosition(char),
+}
+
+#[derive(Debug, Error)]
+pub enum RuleFileError {
+    #[error("failed to read the rule file: {0}")]
+    Io(#[from] io::Error),
+    #[error("line {line}: {error}")]
+    Rule {
+        line: usize,
+        #[source]
+        error: RuleError,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Noop,
+    Lowercase,
+    Uppercase,
+    Capitalize,
+    InvertCapitalize,
+    ToggleCase,
+    ToggleAt(usize),
+    Reverse,
+    Duplicate,
+    Append(u8),
+    Prepend(u8),
+    Replace(u8, u8),
+}
+
+/// A sequence of functions that turns a word into a candidate.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rule {
+    functions: Vec<Function>,
+}
+
+impl Rule {
+    /// Parses a rule such as `c $1 $2 sa@`.
+    pub fn parse(rule: impl AsRef<[u8]>) -> Result<Self, RuleError> {
+        let mut functions = Vec::new();
+        let mut bytes = rule.as_ref().iter().copied();
+        while let Some(name) = bytes.next() {
+            let mut argument = || bytes.next().ok_or(RuleError::MissingArgument(name as char));
+            functions.push(match name {
+                b' ' => continue,
+                b':' => Function::Noop,
+                b'l' => Function::Lowercase,
+                b'u' => Function::Uppercase,
+                b'c' => Function::Capitalize,
+                b'C' => Function::InvertCapitalize,
+                b't' => Function::ToggleCase,
+                b'T' => Function::ToggleAt(position(argument()?)?),
+                b'r' => Function::Reverse,
+                b'd' => Function::Duplicate,
+                b'$' => Function::Append(argument()?),
+                b'^' => Function::Prepend(argument()?),
+                b's' => Function::Replace(argument()?, argument()?),
+                _ => return Err(RuleError::UnknownFunction(name as char)),
+            });
+        }
+        Ok(Self { functions })
+    }
+
+    /// Replaces the letters of common leetspeak with their digits.
+    pub fn leet() -> Self {
+        Self::parse("sa4 se3 si1 so0 ss5 st7").expect("the leet rule is valid")
+    }
+
+    /// Appends `word` with the rule applied to `plaintext`.
+    pub fn apply(&self, word: &[u8], plaintext: &mut Vec<u8>) {
+        let start = plaintext.len();
+        plaintext.extend_from_slice(word);
+        for function in &self.functions {
+            let word = &mut plaintext[start..];
+            match *function {
+                Function::Noop => {}
+                Function::Lowercase => word.make_ascii_lowercase(),
+                Function::Uppercase => word.make_ascii_uppercase(),
+                Function::Capitalize | Function::InvertCapitalize => {
+                    let capitalize = *function == Function::Capitalize;
+                    if let Some((first, rest)) = word.split_first_mut() {
+                        if capitalize {
+                            first.make_ascii_uppercase();
+                            rest.make_ascii_lowercase();
+                        } else {
+                            first.make_ascii_lowercase();
+                            rest.make_ascii_uppercase();
+                        }
+                    }
+                }
+                Function::ToggleCase => word.iter_mut().for_each(toggle_case),
+                Function::ToggleAt(pos) => {
+                    if let Some(c) = word.get_mut(pos) {
+                        toggle_case(c);
+                    }
+                }
+                Function::Reverse => word.reverse(),
+                Function::Duplicate => plaintext.extend_from_within(start..),
+                Function::Append(c) => plaintext.push(c),
+                Function::Prepend(c) => plaintext.insert(start, c),
+                Function::Replace(from, to) => {
+                    for c in word.iter_mut().filter(|c| **c == from) {
+                        *c = to;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn toggle_case(c: &mut u8) {
+    if c.is_ascii_alphabetic() {
+        *c ^= 0x20;
+    }
+}
+
+fn position(c: u8) -> Result<usize, RuleError> {
+    match c {
+        b'0'..=b'9' => Ok((c - b'0') as usize),
+        b'A'..=b'Z' => Ok((c - b'A') as usize + 10),
+        _ => Err(RuleError::InvalidPosition(c as char)),
+    }
+}
+
+/// Parses the contents of a rule file, one rule per line. Empty lines and
+/// lines starting with `#` are skipped.
+pub fn parse_rules(contents: &str) -> Result<Vec<Rule>, RuleFileError> {
+    let mut rules = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let rule = Rule::parse(line).map_err(|error| RuleFileError::Rule { line: i + 1, error })?;
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+/// Reads a rule file. See [`parse_rules`].
+pub fn read_rules(path: impl AsRef<Path>) -> Result<Vec<Rule>, RuleFileError> {
+    parse_rules(&std::fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_rules, Rule, RuleError, RuleFileError};
+
+    fn apply(rule: &str, word: &str) -> String {
+        let mut plaintext = b">".to_vec();
+        Rule::parse(rule)
+            .unwrap()
+            .apply(word.as_bytes(), &mut plaintext);
+        assert_eq!(plaintext[0], b'>');
+        String::from_utf8(plaintext[1..].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_rule() {
+        assert_eq!(apply(":", "Pass"), "Pass");
+        assert_eq!(apply("l", "PaSs1"), "pass1");
+        assert_eq!(apply("u", "PaSs1"), "PASS1");
+        assert_eq!(apply("c", "pASS"), "Pass");
+        assert_eq!(apply("C", "Pass"), "pASS");
+        assert_eq!(apply("t", "PaSs1"), "pAsS1");
+        assert_eq!(apply("T0 T3 TZ", "pass"), "PasS");
+        assert_eq!(apply("r", "abc"), "cba");
+        assert_eq!(apply("d", "ab"), "abab");
+        assert_eq!(apply("c $2 $0 ^!", "word"), "!Word20");
+        assert_eq!(apply("$ ", "a"), "a ");
+        assert_eq!(apply("ss$", "pass"), "pa$$");
+        let mut plaintext = Vec::new();
+        Rule::leet().apply(b"password", &mut plaintext);
+        assert_eq!(plaintext, b"p455w0rd");
+
+        assert_eq!(Rule::parse("x"), Err(RuleError::UnknownFunction('x')));
+        assert_eq!(Rule::parse("c $"), Err(RuleError::MissingArgument('$')));
+        assert_eq!(Rule::parse("sa"), Err(RuleError::MissingArgument('s')));
+        assert_eq!(Rule::parse("T!"), Err(RuleError::InvalidPosition('!')));
+
+        let rules = parse_rules("# comment\n:\n\nu\r\n$1 $2\n").unwrap();
+        assert_eq!(rules.len(), 3);
+        match parse_rules(":\nq") {
+            Err(RuleFileError::Rule { line, error }) => {
+                assert_eq!(line, 2);
+                assert_eq!(error, RuleError::UnknownFunction('q'));
+            }
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Hands out work units, ranges of candidate indices, to search workers.
+//!
+//! Every worker owns a deque. It takes the next unit from its own end and
+//! leaves the rest of the range behind as two halves, which idle workers steal
+//! from the other end. Once every deque is empty, a worker claims the next
+//! segment of the keyspace. Segments are claimed in order, so the search
+//! moves through the keyspace from the front even with many workers.
+
+use std::{ops::Range, sync::Mutex};
+
+use crossbeam::deque::{Steal, Stealer, Worker};
+
+/// The number of units in a segment claimed by one worker.
+const SEGMENT_UNITS: u128 = 16;
+
+pub(crate) struct Scheduler {
+    /// The first index no worker has claimed yet.
+    cursor: Mutex<u128>,
+    end: u128,
+    stealers: Vec<Stealer<Range<u128>>>,
+}
+
+/// The deque of one worker.
+pub(crate) struct LocalQueue {
+    deque: Worker<Range<u128>>,
+    index: usize,
+}
+
+impl Scheduler {
+    /// Splits the indices `0..end` among `thread_count` workers.
+    pub(crate) fn new(end: u128, thread_count: usize) -> (Self, Vec<LocalQueue>) {
+        let queues: Vec<LocalQueue> = (0..thread_count)
+            .map(|index| LocalQueue {
+                deque: Worker::new_lifo(),
+                index,
+            })
+            .collect();
+        let scheduler = Self {
+            cursor: Mutex::new(0),
+            end,
+            stealers: queues.iter().map(|q| q.deque.stealer()).collect(),
+        };
+        (scheduler, queues)
+    }
+
+    /// The next unit of at most `unit` indices below `limit` for the worker
+    /// owning `local`, or `None` once no such work is left.
+    pub(crate) fn next(&self, local: &LocalQueue, unit: u128, limit: u128) -> Option<Range<u128>> {
+        let range = loop {
+            let range = local
+                .deque
+                .pop()
+                .or_else(|| self.steal(local))
+                .or_else(|| self.claim(unit.saturating_mul(SEGMENT_UNITS), limit))?;
+            // Ranges past the limit are dropped without being searched
+            if range.start < limit {
+                break range.start..range.end.min(limit);
+            }
+        };
+        if range.end - range.start <= unit {
+            return Some(range);
+        }
+        let rest = range.start + unit..range.end;
+        if rest.end - rest.start > unit {
+            // The upper half goes in first, to be stolen before the lower one
+            let mid = rest.start + (rest.end - rest.start) / 2;
+            local.deque.push(mid..rest.end);
+            local.deque.push(rest.start..mid);
+        } else {
+            local.deque.push(rest);
+        }
+        Some(range.start..range.start + unit)
+    }
+
+    fn steal(&self, local: &LocalQueue) -> Option<Range<u128>> {
+        let n = self.stealers.len();
+        loop {
+            let mut retry = false;
+            for i in (1..n).map(|k| (local.index + k) % n) {
+                match self.stealers[i].steal() {
+                    Steal::Success(range) => return Some(range),
+                    Steal::Retry => retry = true,
+                    Steal::Empty => {}
+                }
+            }
+            if !retry {
+                return None;
+            }
+        }
+    }
+
+    fn claim(&self, size: u128, limit: u128) -> Option<Range<u128>> {
+        let mut cursor = self.cursor.lock().unwrap();
+        let end = self.end.min(limit);
+        if *cursor >= end {
+            return None;
+        }
+        let start = *cursor;
+        *cursor = start.saturating_add(size).min(end);
+        Some(start..*cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scheduler;
+
+    #[test]
+    fn test_scheduler() {
+        // One worker sees every index exactly once, in order
+        let (scheduler, queues) = Scheduler::new(1000, 1);
+        let mut next = 0;
+        while let Some(range) = scheduler.next(&queues[0], 7, u128::MAX) {
+            assert_eq!(range.start, next);
+            assert!(range.end - range.start <= 7);
+            next = range.end;
+        }
+        assert_eq!(next, 1000);
+
+        // Stolen halves and the limit still cover each index once
+        let (scheduler, queues) = Scheduler::new(u128::MAX, 3);
+        let mut seen = vec![];
+        for round in 0.. {
+            let queue = &queues[round % 3];
+            match scheduler.next(queue, 5, 500) {
+                Some(range) => seen.extend(range),
+                None if queues.iter().all(|q| q.deque.is_empty()) => break,
+                None => {}
+            }
+        }
+        seen.sort_unstable();
+        assert_eq!(seen, (0..500).collect::<Vec<_>>());
+
+        let (scheduler, queues) = Scheduler::new(0, 1);
+        assert_eq!(scheduler.next(&queues[0], 1, u128::MAX), None);
+    }
+}
@@ -0,0 +1,379 @@
+//! The "sloth" proof of work used by kCTF and pwn.red.
+//!
+//! A challenge `s.<difficulty>.<x>` is solved by iterating
+//! `x = x^((p + 1) / 4) ^ 1 (mod p)` `difficulty` times with `p = 2^1279 - 1`,
+//! and verified by iterating `y = (y ^ 1)^2 (mod p)`. Since `(p + 1) / 4` is
+//! `2^1277`, each step of the solver is 1277 modular squarings.
+
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::encoding::{base64_decode, base64_encode};
+
+pub const VERSION: &str = "s";
+
+const LIMBS: usize = 20;
+const MODULUS_BITS: usize = 1279;
+const TOP_MASK: u64 = (1 << 63) - 1;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ParseSlothError {
+    #[error("unknown challenge version")]
+    InvalidVersion,
+    #[error("invalid challenge format")]
+    InvalidFormat,
+    #[error("invalid base64")]
+    InvalidBase64,
+    #[error("number is too large")]
+    NumberTooLarge,
+}
+
+/// How numbers are serialized. Both are accepted when parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// kCTF's `pow.py`: every number is padded to a multiple of 3 bytes.
+    Kctf,
+    /// pwn.red: the difficulty is 4 bytes and other numbers have no leading zeros.
+    PwnRed,
+}
+
+/// A residue modulo `2^1279 - 1` as little-endian limbs, below `2^1279`
+/// but not necessarily fully reduced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Residue([u64; LIMBS]);
+
+impl Residue {
+    const MODULUS: Residue = {
+        let mut limbs = [u64::MAX; LIMBS];
+        limbs[LIMBS - 1] = TOP_MASK;
+        Residue(limbs)
+    };
+
+    /// Reduces an arbitrary little-endian number by summing its 1279-bit chunks.
+    fn from_limbs(limbs: &[u64]) -> Self {
+        let bit_word = |bit: usize| -> u64 {
+            let (word, shift) = (bit / 64, bit % 64);
+            let lo = limbs.get(word).copied().unwrap_or(0) >> shift;
+            let hi = match shift {
+                0 => 0,
+                _ => limbs.get(word + 1).copied().unwrap_or(0) << (64 - shift),
+            };
+            lo | hi
+        };
+        let mut acc = [0u64; LIMBS];
+        let mut start = 0;
+        while start < limbs.len() * 64 {
+            let mut carry = false;
+            for (i, a) in acc.iter_mut().enumerate() {
+                let mut chunk = bit_word(start + i * 64);
+                if i == LIMBS - 1 {
+                    chunk &= TOP_MASK;
+                }
+                let (s1, c1) = a.overflowing_add(chunk);
+                let (s2, c2) = s1.overflowing_add(carry as u64);
+                *a = s2;
+                carry = c1 || c2;
+            }
+            // Both summands are below 2^1279, so the sum fits in 1280 bits
+            let mut top = acc[LIMBS - 1] >> 63;
+            acc[LIMBS - 1] &= TOP_MASK;
+            for a in acc.iter_mut() {
+                if top == 0 {
+                    break;
+                }
+                let (s, c) = a.overflowing_add(top);
+                *a = s;
+                top = c as u64;
+            }
+            start += MODULUS_BITS;
+        }
+        Residue(acc)
+    }
+
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        let limbs: Vec<u64> = bytes
+            .rchunks(8)
+            .map(|chunk| {
+                let mut word = [0u8; 8];
+                word[8 - chunk.len()..].copy_from_slice(chunk);
+                u64::from_be_bytes(word)
+            })
+            .collect();
+        Self::from_limbs(&limbs)
+    }
+
+    /// Big-endian bytes without leading zeros.
+    fn to_be_bytes(self) -> Vec<u8> {
+        let bytes: Vec<u8> = self.0.iter().rev().flat_map(|l| l.to_be_bytes()).collect();
+        let first = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+        bytes[first..].to_vec()
+    }
+
+    fn square(&self) -> Self {
+        let a = &self.0;
+        let mut t = [0u64; 2 * LIMBS];
+        for i in 0..LIMBS {
+            let mut carry = 0u64;
+            for j in 0..LIMBS {
+                let v = t[i + j] as u128 + a[i] as u128 * a[j] as u128 + carry as u128;
+                t[i + j] = v as u64;
+                carry = (v >> 64) as u64;
+            }
+            t[i + LIMBS] = carry;
+        }
+        Self::from_limbs(&t)
+    }
+
+    fn canonical(self) -> Self {
+        if self == Self::MODULUS {
+            Residue([0; LIMBS])
+        } else {
+            self
+        }
+    }
+
+    /// `p - self` for a canonical `self`.
+    fn negate(self) -> Self {
+        let mut ret = Self::MODULUS;
+        let mut borrow = false;
+        for (r, &a) in ret.0.iter_mut().zip(&self.0) {
+            let (s1, b1) = r.overflowing_sub(a);
+            let (s2, b2) = s1.overflowing_sub(borrow as u64);
+            *r = s2;
+            borrow = b1 || b2;
+        }
+        ret
+    }
+}
+
+fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let first = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    &bytes[first..]
+}
+
+fn encode_number(bytes: &[u8], encoding: Encoding, out: &mut Vec<u8>) {
+    let bytes = strip_leading_zeros(bytes);
+    match encoding {
+        Encoding::Kctf => {
+            let bit_length = match bytes.first() {
+                Some(&first) => bytes.len() * 8 - first.leading_zeros() as usize,
+                None => 0,
+            };
+            let size = bit_length / 24 * 3 + 3;
+            let mut padded = vec![0u8; size - bytes.len()];
+            padded.extend_from_slice(bytes);
+            base64_encode(&padded, out);
+        }
+        Encoding::PwnRed => base64_encode(bytes, out),
+    }
+}
+
+fn decode_number(s: &str) -> Result<Vec<u8>, ParseSlothError> {
+    let bytes = base64_decode(s).ok_or(ParseSlothError::InvalidBase64)?;
+    Ok(strip_leading_zeros(&bytes).to_vec())
+}
+
+fn decode_fixed<const N: usize>(s: &str) -> Result<[u8; N], ParseSlothError> {
+    let bytes = decode_number(s)?;
+    if bytes.len() > N {
+        return Err(ParseSlothError::NumberTooLarge);
+    }
+    let mut ret = [0u8; N];
+    ret[N - bytes.len()..].copy_from_slice(&bytes);
+    Ok(ret)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Challenge {
+    pub difficulty: u32,
+    pub x: u128,
+}
+
+impl Challenge {
+    /// A challenge with a random 128-bit `x`.
+    pub fn generate(difficulty: u32) -> Result<Self, getrandom::Error> {
+        let mut x = [0u8; 16];
+        getrandom::getrandom(&mut x)?;
+        Ok(Self {
+            difficulty,
+            x: u128::from_be_bytes(x),
+        })
+    }
+
+    pub fn encode(&self, encoding: Encoding) -> String {
+        let mut out = format!("{}.", VERSION).into_bytes();
+        match encoding {
+            Encoding::Kctf => encode_number(&self.difficulty.to_be_bytes(), encoding, &mut out),
+            Encoding::PwnRed => base64_encode(&self.difficulty.to_be_bytes(), &mut out),
+        }
+        out.push(b'.');
+        encode_number(&self.x.to_be_bytes(), encoding, &mut out);
+        String::from_utf8(out).expect("base64 is ascii")
+    }
+
+    /// Computes the solution. This takes `1277 * difficulty` modular squarings.
+    pub fn solve(&self) -> Solution {
+        let mut x = Residue::from_be_bytes(&self.x.to_be_bytes());
+        for _ in 0..self.difficulty {
+            for _ in 0..MODULUS_BITS - 2 {
+                x = x.square();
+            }
+            x = x.canonical();
+            x.0[0] ^= 1;
+        }
+        Solution { y: x.to_be_bytes() }
+    }
+
+    pub fn verify(&self, solution: &Solution) -> bool {
+        let x = Residue::from_be_bytes(&self.x.to_be_bytes());
+        let neg_x = x.negate();
+        if self.difficulty == 0 {
+            let y = strip_leading_zeros(&solution.y);
+            return y == x.to_be_bytes() || y == neg_x.to_be_bytes();
+        }
+
+        // The first bit flip applies to the unreduced number
+        let mut y = solution.y.clone();
+        match y.last_mut() {
+            Some(last) => *last ^= 1,
+            None => y.push(1),
+        }
+        let mut y = Residue::from_be_bytes(&y).square().canonical();
+        for _ in 1..self.difficulty {
+            y.0[0] ^= 1;
+            y = y.square().canonical();
+        }
+        y == x || y == neg_x
+    }
+}
+
+impl FromStr for Challenge {
+    type Err = ParseSlothError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split('.').collect();
+        if parts[0] != VERSION {
+            return Err(ParseSlothError::InvalidVersion);
+        }
+        if parts.len() != 3 {
+            return Err(ParseSlothError::InvalidFormat);
+        }
+        Ok(Self {
+            difficulty: u32::from_be_bytes(decode_fixed(parts[1])?),
+            x: u128::from_be_bytes(decode_fixed(parts[2])?),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    /// Big-endian bytes without leading zeros.
+    y: Vec<u8>,
+}
+
+impl Solution {
+    pub fn encode(&self, encoding: Encoding) -> String {
+        let mut out = format!("{}.", VERSION).into_bytes();
+        encode_number(&self.y, encoding, &mut out);
+        String::from_utf8(out).expect("base64 is ascii")
+    }
+}
+
+impl FromStr for Solution {
+    type Err = ParseSlothError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split('.').collect();
+        if parts[0] != VERSION {
+            return Err(ParseSlothError::InvalidVersion);
+        }
+        if parts.len() != 2 {
+            return Err(ParseSlothError::InvalidFormat);
+        }
+        Ok(Self {
+            y: decode_number(parts[1])?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Challenge, Encoding, ParseSlothError, Solution};
+
+    #[test]
+    fn test_solve_and_verify() {
+        let challenge: Challenge = "s.AAAK.AAASNFZ4kKvN7xEiM0RVZneI".parse().unwrap();
+        assert_eq!(
+            challenge,
+            Challenge {
+                difficulty: 10,
+                x: 0x1234567890abcdef1122334455667788
+            }
+        );
+        assert_eq!(
+            "s.AAAACg==.EjRWeJCrze8RIjNEVWZ3iA==".parse::<Challenge>(),
+            Ok(challenge)
+        );
+        assert_eq!(
+            challenge.encode(Encoding::PwnRed),
+            "s.AAAACg==.EjRWeJCrze8RIjNEVWZ3iA=="
+        );
+        assert_eq!(
+            challenge.encode(Encoding::Kctf),
+            "s.AAAK.AAASNFZ4kKvN7xEiM0RVZneI"
+        );
+
+        let solution = challenge.solve();
+        assert_eq!(
+            solution.encode(Encoding::PwnRed),
+            "s.D1N8LtLRGKiRP4Zw+++b7ae/DgDpQn6/tRjI+1VR77H/OqRq3uzzIE90RL7+ea0Q2AuRXU6N7lDkBpexb3Y/\
+             mgcEJA7xred5xq9fIuD6iRo9+Xt0FS21fhNscjAwECpNzgqj1wiA4qMg8h59gZbIcVWjNSWSXX/qjsvX7lxWC3N\
+             L9cNPhrrdPo1VKAvEfF0Kf6Y8U2pqldQbTYOLZzO0Mw=="
+        );
+        let kctf = solution.encode(Encoding::Kctf);
+        assert!(kctf.starts_with("s.AAAPU3wu0tEYqJE/hnD775vtp78OAOlCfr+1GMj7VVHvsf86"));
+        assert_eq!(kctf.parse::<Solution>(), Ok(solution.clone()));
+        assert!(challenge.verify(&solution));
+
+        let wrong = Challenge {
+            difficulty: 10,
+            x: 0x1234567890abcdef1122334455667789,
+        };
+        assert!(!wrong.verify(&solution));
+    }
+
+    #[test]
+    fn test_generate() {
+        let challenge = Challenge::generate(3).unwrap();
+        let solution = challenge.solve();
+        assert!(challenge.verify(&solution));
+        assert_eq!(
+            challenge
+                .encode(Encoding::Kctf)
+                .parse::<Challenge>()
+                .unwrap(),
+            challenge
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(
+            "t.AAAK.AAAK".parse::<Challenge>(),
+            Err(ParseSlothError::InvalidVersion)
+        );
+        assert_eq!(
+            "s.AAAK".parse::<Challenge>(),
+            Err(ParseSlothError::InvalidFormat)
+        );
+        assert_eq!(
+            "s.AAAK.!!!!".parse::<Challenge>(),
+            Err(ParseSlothError::InvalidBase64)
+        );
+        assert_eq!(
+            "s.AQAAAAA=.AAAK".parse::<Challenge>(),
+            Err(ParseSlothError::NumberTooLarge)
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::Instant,
+};
+
+#[cfg(feature = "async")]
+use crate::start_search_by_hash_type_on;
+use crate::{
+    check_alphabets, search_by_hash_type_on, start_search_on, wait_for_done, Alphabet, Hash,
+    HashType, Job, PowError, PowSearchParameters, Runner, SearchReport, ThreadConfig,
+};
+
+/// A pool of worker threads that runs many searches without starting threads
+/// for each one.
+///
+/// Searches block the calling thread until the pool has hashed them, so
+/// several threads can share one solver. The workers of a
+/// search queue up behind those of earlier searches, and
+/// [`ThreadConfig::priority`] lets urgent searches jump the queue. Dropping
+/// the solver lets queued work finish and then stops its threads.
+#[derive(Debug)]
+pub struct Solver {
+    shared: Arc<Shared>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    available: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    jobs: BinaryHeap<Queued>,
+    /// Keeps jobs of the same priority in submission order.
+    next_seq: u64,
+    shutdown: bool,
+}
+
+struct Queued {
+    priority: i32,
+    seq: u64,
+    job: Job<'static>,
+}
+
+impl std::fmt::Debug for Queued {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Queued")
+            .field("priority", &self.priority)
+            .field("seq", &self.seq)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl Solver {
+    /// Starts `thread_count` worker threads, one per CPU if `None`, and at
+    /// least one.
+    pub fn new(thread_count: Option<usize>) -> Self {
+        let thread_count = thread_count
+            .unwrap_or_else(|| {
+                std::thread::available_parallelism()
+                    .map(|a| a.get())
+                    .unwrap_or(2)
+            })
+            .max(1);
+        let shared = Arc::new(Shared::default());
+        let threads = (0..thread_count)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || shared.work())
+            })
+            .collect();
+        Self { shared, threads }
+    }
+
+    pub fn thread_count(&self) -> usize {
+        self.threads.len()
+    }
+
+    fn submit(&self, priority: i32, job: Job<'static>) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        let seq = queue.next_seq;
+        queue.next_seq += 1;
+        queue.jobs.push(Queued { priority, seq, job });
+        self.shared.available.notify_one();
+    }
+
+    /// Same as [`search`](crate::search), but hashes on the pool.
+    pub fn search<H: Hash + Default + 'static>(
+        &self,
+        params: PowSearchParameters<H, impl Alphabet + 'static>,
+        thread_config: &ThreadConfig,
+    ) -> Result<SearchReport<H::Output>, PowError> {
+        self.search_with_hasher(H::default(), params, thread_config)
+    }
+
+    /// Same as [`search_with_hasher`](crate::search_with_hasher), but hashes
+    /// on the pool. [`ThreadConfig::thread_count`] is the number of pool
+    /// threads the search occupies at most, all of them if `None`.
+    pub fn search_with_hasher<H: Hash + 'static>(
+        &self,
+        hasher: H,
+        params: PowSearchParameters<H, impl Alphabet + 'static>,
+        thread_config: &ThreadConfig,
+    ) -> Result<SearchReport<H::Output>, PowError> {
+        wait_for_done(|done| {
+            self.start(hasher, params, thread_config, move |result| {
+                let _ = done.send(result);
+            })
+        })?
+    }
+
+    /// Same as [`search_by_hash_type`](crate::search_by_hash_type), but
+    /// hashes on the pool.
+    pub fn search_by_hash_type(
+        &self,
+        hash_type: HashType,
+        target_hash: &[u8],
+        target_hash_mask: &[u8],
+        plaintext_character_map: &[impl Alphabet + Clone + 'static],
+        thread_config: &ThreadConfig,
+    ) -> Result<SearchReport<Vec<u8>>, PowError> {
+        search_by_hash_type_on(
+            self,
+            hash_type,
+            target_hash,
+            target_hash_mask,
+            plaintext_character_map,
+            thread_config,
+        )
+    }
+
+    /// Same as [`search_by_hash_type`](Self::search_by_hash_type), but
+    /// returns at once and passes the result to `on_done` from a pool
+    /// thread. Picking the hash function, and the linear solve of CRCs, run
+    /// on the pool too.
+    #[cfg(feature = "async")]
+    pub(crate) fn start_search_by_hash_type(
+        &'static self,
+        hash_type: HashType,
+        target_hash: Vec<u8>,
+        target_hash_mask: Vec<u8>,
+        plaintext_character_map: Vec<impl Alphabet + Clone + 'static>,
+        thread_config: ThreadConfig,
+        on_done: impl FnOnce(Result<SearchReport<Vec<u8>>, PowError>) + Send + 'static,
+    ) {
+        self.submit(
+            thread_config.priority,
+            Box::new(move || {
+                start_search_by_hash_type_on(
+                    self,
+                    hash_type,
+                    &target_hash,
+                    &target_hash_mask,
+                    &plaintext_character_map,
+                    &thread_config,
+                    on_done,
+                )
+            }),
+        );
+    }
+}
+
+impl Runner for Solver {
+    fn worker_count(&self, thread_config: &ThreadConfig) -> usize {
+        thread_config
+            .thread_count
+            .unwrap_or_else(|| self.thread_count())
+            .max(1)
+    }
+
+    /// Queues the workers of the search on the pool and returns.
+    fn start<H: Hash + 'static, A: Alphabet + 'static>(
+        &self,
+        hasher: H,
+        params: PowSearchParameters<H, A>,
+        thread_config: &ThreadConfig,
+        on_done: impl FnOnce(Result<SearchReport<H::Output>, PowError>) + Send + 'static,
+    ) {
+        let start = Instant::now();
+        if let Err(e) = check_alphabets(&params.plaintext_character_map) {
+            return on_done(Err(e));
+        }
+        start_search_on(
+            hasher,
+            params,
+            thread_config,
+            self.worker_count(thread_config),
+            start,
+            |job| self.submit(thread_config.priority, job),
+            on_done,
+        )
+    }
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl Drop for Solver {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().shutdown = true;
+        self.shared.available.notify_all();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Shared {
+    fn work(&self) {
+        loop {
+            let job = {
+                let mut queue = self.queue.lock().unwrap();
+                loop {
+                    if let Some(queued) = queue.jobs.pop() {
+                        break queued.job;
+                    }
+                    if queue.shutdown {
+                        return;
+                    }
+                    queue = self.available.wait(queue).unwrap();
+                }
+            };
+            // Jobs report their own panics; this only keeps the thread alive
+            let _ = panic::catch_unwind(AssertUnwindSafe(job));
+        }
+    }
+}
+
+#[cfg(all(
+    test,
+    any(feature = "backend-openssl", feature = "backend-rust-crypto")
+))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::Solver;
+    use crate::{HashType, SearchOutcome, ThreadConfig};
+
+    #[test]
+    fn test_solver() {
+        let solver = Solver::new(Some(2));
+        let thread_config = ThreadConfig::default();
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..10 {
+                        let report = solver
+                            .search_by_hash_type(
+                                HashType::Md5,
+                                &[0; 16],
+                                &[0xf0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+                                &vec![(b'a'..=b'z').collect::<Vec<u8>>(); 4],
+                                &thread_config,
+                            )
+                            .unwrap();
+                        assert_eq!(report.solution().unwrap().digest[0] & 0xf0, 0);
+                        assert_eq!(report.thread_hashes.len(), 3);
+                    }
+                });
+            }
+        });
+        let report = solver
+            .search_by_hash_type(
+                HashType::Crc32,
+                &[0; 4],
+                &[0xff; 4],
+                &vec![(0..=255).collect::<Vec<u8>>(); 4],
+                &thread_config,
+            )
+            .unwrap();
+        assert!(matches!(report.outcome, SearchOutcome::Found(_)));
+
+        // An empty pool would leave every search waiting forever
+        let solver = Solver::new(Some(0));
+        assert_eq!(solver.thread_count(), 1);
+        let report = solver
+            .search_by_hash_type(
+                HashType::Md5,
+                &[0; 16],
+                &[0; 16],
+                &[b"ab".to_vec()],
+                &thread_config,
+            )
+            .unwrap();
+        assert!(matches!(report.outcome, SearchOutcome::Found(_)));
+
+        // Queued jobs run by priority, then in submission order
+        let solver = Solver::new(Some(1));
+        let (release_tx, release_rx) = crossbeam::channel::bounded::<()>(0);
+        solver.submit(
+            0,
+            Box::new(move || {
+                let _ = release_rx.recv();
+            }),
+        );
+        let order = Arc::new(Mutex::new(Vec::new()));
+        for (i, priority) in [0, 5, 1, 5].into_iter().enumerate() {
+            let order = order.clone();
+            solver.submit(priority, Box::new(move || order.lock().unwrap().push(i)));
+        }
+        release_tx.send(()).unwrap();
+        drop(solver);
+        assert_eq!(*order.lock().unwrap(), [1, 3, 2, 0]);
+    }
+}
@@ -0,0 +1,162 @@
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::{encoding::BASE64_CHARS, Keyspace};
+
+#[derive(Debug, Error)]
+#[error("invalid charset name")]
+pub struct InvalidCharsetError;
+
+/// A named alphabet for the variable positions of a [`PlaintextTemplate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Charset {
+    /// `0-9`
+    Digits,
+    /// `0-9a-f`
+    Hex,
+    /// `0-9A-Za-z`
+    Alnum,
+    /// Printable ASCII, from space to `~`
+    Printable,
+    /// `A-Za-z0-9+/`
+    Base64,
+}
+
+impl Charset {
+    pub fn bytes(self) -> &'static [u8] {
+        const PRINTABLE: [u8; 95] = {
+            let mut chars = [0; 95];
+            let mut i = 0;
+            while i < chars.len() {
+                chars[i] = b' ' + i as u8;
+                i += 1;
+            }
+            chars
+        };
+        match self {
+            Self::Digits => b"0123456789",
+            Self::Hex => b"0123456789abcdef",
+            Self::Alnum => b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+            Self::Printable => &PRINTABLE,
+            Self::Base64 => BASE64_CHARS,
+        }
+    }
+}
+
+impl FromStr for Charset {
+    type Err = InvalidCharsetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "digits" => Ok(Self::Digits),
+            "hex" => Ok(Self::Hex),
+            "alnum" => Ok(Self::Alnum),
+            "printable" => Ok(Self::Printable),
+            "base64" => Ok(Self::Base64),
+            _ => Err(InvalidCharsetError),
+        }
+    }
+}
+
+/// Builds a `plaintext_character_map` from fixed and variable segments.
+///
+/// Fixed segments take one position per byte, variable ones one position
+/// per character to try, and any template can be repeated as a segment of a
+/// larger one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlaintextTemplate {
+    positions: Vec<Vec<u8>>,
+}
+
+impl PlaintextTemplate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `bytes` as they are, one position per byte.
+    pub fn fixed(mut self, bytes: impl AsRef<[u8]>) -> Self {
+        self.positions
+            .extend(bytes.as_ref().iter().map(|&c| vec![c]));
+        self
+    }
+
+    /// Appends `length` positions that each take any byte of `chars`.
+    pub fn variable(mut self, chars: impl AsRef<[u8]>, length: usize) -> Self {
+        self.positions.extend(vec![chars.as_ref().to_vec(); length]);
+        self
+    }
+
+    /// Appends `length` positions that each take any byte of `charset`.
+    pub fn charset(self, charset: Charset, length: usize) -> Self {
+        self.variable(charset.bytes(), length)
+    }
+
+    /// Appends the positions of `segment` `times` times.
+    pub fn repeat(mut self, segment: &PlaintextTemplate, times: usize) -> Self {
+        for _ in 0..times {
+            self.positions.extend_from_slice(&segment.positions);
+        }
+        self
+    }
+
+    /// The number of positions, which is the length of every plaintext.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn plaintext_character_map(&self) -> &[Vec<u8>] {
+        &self.positions
+    }
+
+    pub fn keyspace(&self) -> Keyspace {
+        Keyspace::new(self.positions.clone())
+    }
+
+    /// The `plaintext_character_map` of the template.
+    pub fn build(self) -> Vec<Vec<u8>> {
+        self.positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use super::{Charset, PlaintextTemplate};
+
+    #[test]
+    fn test_template() {
+        let template = PlaintextTemplate::new()
+            .fixed("ab")
+            .charset(Charset::Digits, 2)
+            .repeat(&PlaintextTemplate::new().fixed(".").variable("xy", 1), 2)
+            .fixed(b"");
+        assert_eq!(template.len(), 8);
+        assert_eq!(*template.keyspace().size(), BigUint::from(400u32));
+        assert_eq!(
+            template.build(),
+            [
+                &b"a"[..],
+                b"b",
+                b"0123456789",
+                b"0123456789",
+                b".",
+                b"xy",
+                b".",
+                b"xy"
+            ]
+        );
+
+        assert_eq!("HEX".parse::<Charset>().unwrap(), Charset::Hex);
+        assert!("octal".parse::<Charset>().is_err());
+        assert_eq!(Charset::Alnum.bytes().len(), 62);
+        assert_eq!(Charset::Base64.bytes().len(), 64);
+        let printable = Charset::Printable.bytes();
+        assert_eq!((printable[0], printable[94]), (b' ', b'~'));
+    }
+}
@@ -0,0 +1,257 @@
+//! Rivest-Shamir-Wagner time-lock puzzles.
+//!
+//! A puzzle `(n, a, t)` asks for `a^(2^t) mod n`. Without the factorization
+//! of the RSA modulus `n` this takes `t` sequential modular squarings, which
+//! cannot be sped up by more threads. With the factorization, the exponent
+//! can be reduced modulo `phi(n)` first.
+
+use std::time::{Duration, Instant};
+
+use num_bigint::BigUint;
+use thiserror::Error;
+
+use crate::CancellationToken;
+
+/// How many squarings are done between progress reports and cancellation
+/// checks.
+pub const CHECK_INTERVAL: u64 = 1024;
+
+const MILLER_RABIN_ROUNDS: usize = 40;
+const SMALL_PRIMES: [u32; 24] = [
+    3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+#[derive(Debug, Error)]
+pub enum TimeLockError {
+    #[error("modulus must be at least 64 bits")]
+    ModulusTooSmall,
+    #[error("failed to generate random numbers: {0}")]
+    Random(#[from] getrandom::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    pub n: BigUint,
+    pub a: BigUint,
+    /// The number of squarings.
+    pub t: u64,
+}
+
+/// The factorization of a puzzle's modulus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trapdoor {
+    p: BigUint,
+    q: BigUint,
+}
+
+fn random_below_bits(bits: u64) -> Result<BigUint, getrandom::Error> {
+    let mut bytes = vec![0u8; bits.div_ceil(8) as usize];
+    getrandom::getrandom(&mut bytes)?;
+    let excess = bytes.len() as u64 * 8 - bits;
+    bytes[0] &= 0xff >> excess;
+    Ok(BigUint::from_bytes_be(&bytes))
+}
+
+/// A random number in `[2, n - 2]` for `n > 4`.
+fn random_base(n: &BigUint) -> Result<BigUint, getrandom::Error> {
+    let range = n - 3u32;
+    Ok(random_below_bits(n.bits() + 64)? % range + 2u32)
+}
+
+fn is_probable_prime(n: &BigUint) -> Result<bool, getrandom::Error> {
+    for p in SMALL_PRIMES {
+        if n % p == BigUint::ZERO {
+            return Ok(*n == BigUint::from(p));
+        }
+    }
+    let n_minus_one = n - 1u32;
+    let s = n_minus_one.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_one >> s;
+    'witness: for _ in 0..MILLER_RABIN_ROUNDS {
+        let mut x = random_base(n)?.modpow(&d, n);
+        if x == BigUint::from(1u32) || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..s {
+            x = &x * &x % n;
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// A random prime of exactly `bits` bits with the two top bits set, so that
+/// the product of two such primes has exactly `2 * bits` bits.
+fn random_prime(bits: u64) -> Result<BigUint, getrandom::Error> {
+    loop {
+        let mut candidate = random_below_bits(bits)?;
+        candidate.set_bit(bits - 1, true);
+        candidate.set_bit(bits - 2, true);
+        candidate.set_bit(0, true);
+        if is_probable_prime(&candidate)? {
+            return Ok(candidate);
+        }
+    }
+}
+
+impl Puzzle {
+    /// Generates a puzzle with a `modulus_bits`-bit modulus and `t` squarings.
+    pub fn generate(modulus_bits: u64, t: u64) -> Result<(Puzzle, Trapdoor), TimeLockError> {
+        if modulus_bits < 64 {
+            return Err(TimeLockError::ModulusTooSmall);
+        }
+        let p = random_prime(modulus_bits / 2)?;
+        let q = loop {
+            let q = random_prime(modulus_bits - modulus_bits / 2)?;
+            if q != p {
+                break q;
+            }
+        };
+        let n = &p * &q;
+        let a = loop {
+            let a = random_base(&n)?;
+            if &a % &p != BigUint::ZERO && &a % &q != BigUint::ZERO {
+                break a;
+            }
+        };
+        Ok((Puzzle { n, a, t }, Trapdoor { p, q }))
+    }
+
+    /// Computes `a^(2^t) mod n` by repeated squaring.
+    ///
+    /// `progress` is called with the number of squarings done every
+    /// [`CHECK_INTERVAL`] squarings. Returns `None` if `cancel` is cancelled.
+    pub fn solve(
+        &self,
+        cancel: Option<&CancellationToken>,
+        mut progress: impl FnMut(u64),
+    ) -> Option<BigUint> {
+        let mut y = &self.a % &self.n;
+        let mut done = 0;
+        while done < self.t {
+            let steps = CHECK_INTERVAL.min(self.t - done);
+            for _ in 0..steps {
+                y = &y * &y % &self.n;
+            }
+            done += steps;
+            if cancel.is_some_and(|cancel| cancel.is_cancelled()) {
+                return None;
+            }
+            progress(done);
+        }
+        Some(y)
+    }
+}
+
+impl Trapdoor {
+    pub fn p(&self) -> &BigUint {
+        &self.p
+    }
+
+    pub fn q(&self) -> &BigUint {
+        &self.q
+    }
+
+    /// Computes the solution of `puzzle` as `a^(2^t mod phi(n)) mod n`.
+    pub fn solve(&self, puzzle: &Puzzle) -> BigUint {
+        let phi = (&self.p - 1u32) * (&self.q - 1u32);
+        let e = BigUint::from(2u32).modpow(&BigUint::from(puzzle.t), &phi);
+        puzzle.a.modpow(&e, &puzzle.n)
+    }
+
+    /// Checks a claimed solution of `puzzle` without doing the squarings.
+    pub fn verify(&self, puzzle: &Puzzle, solution: &BigUint) -> bool {
+        puzzle.n == &self.p * &self.q && *solution == self.solve(puzzle)
+    }
+}
+
+/// A measured modular squaring rate, used to pick `t` for a target duration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    pub modulus_bits: u64,
+    pub squarings_per_second: f64,
+}
+
+impl Calibration {
+    /// Squares modulo a random `modulus_bits`-bit number for about `sample`.
+    pub fn measure(modulus_bits: u64, sample: Duration) -> Result<Self, TimeLockError> {
+        if modulus_bits < 64 {
+            return Err(TimeLockError::ModulusTooSmall);
+        }
+        let mut n = random_below_bits(modulus_bits)?;
+        n.set_bit(modulus_bits - 1, true);
+        n.set_bit(0, true);
+        let mut y = random_base(&n)?;
+
+        let start = Instant::now();
+        let mut squarings = 0u64;
+        while start.elapsed() < sample || squarings == 0 {
+            for _ in 0..CHECK_INTERVAL {
+                y = &y * &y % &n;
+            }
+            squarings += CHECK_INTERVAL;
+        }
+        Ok(Self {
+            modulus_bits,
+            squarings_per_second: squarings as f64 / start.elapsed().as_secs_f64(),
+        })
+    }
+
+    /// The number of squarings that take about `duration` at this rate.
+    pub fn squarings_for(&self, duration: Duration) -> u64 {
+        (self.squarings_per_second * duration.as_secs_f64()) as u64
+    }
+
+    /// Generates a puzzle that takes about `duration` to solve at this rate.
+    pub fn generate(&self, duration: Duration) -> Result<(Puzzle, Trapdoor), TimeLockError> {
+        Puzzle::generate(self.modulus_bits, self.squarings_for(duration))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use num_bigint::BigUint;
+
+    use super::{is_probable_prime, Calibration, Puzzle, CHECK_INTERVAL};
+    use crate::CancellationToken;
+
+    #[test]
+    fn test_primality() {
+        assert!(is_probable_prime(&BigUint::from(97u32)).unwrap());
+        assert!(is_probable_prime(&BigUint::from(2147483647u32)).unwrap());
+        assert!(!is_probable_prime(&BigUint::from(561u32)).unwrap());
+        // 2^61 - 1 and a product of two primes near 2^32
+        assert!(is_probable_prime(&BigUint::from((1u64 << 61) - 1)).unwrap());
+        assert!(!is_probable_prime(&BigUint::from(4294967291u64 * 4294967279)).unwrap());
+    }
+
+    #[test]
+    fn test_solve_and_verify() {
+        let (puzzle, trapdoor) = Puzzle::generate(256, 3000).unwrap();
+        assert_eq!(puzzle.n.bits(), 256);
+
+        let mut reports = Vec::new();
+        let solution = puzzle.solve(None, |done| reports.push(done)).unwrap();
+        assert_eq!(reports, [CHECK_INTERVAL, 2 * CHECK_INTERVAL, 3000]);
+        assert!(trapdoor.verify(&puzzle, &solution));
+        assert!(!trapdoor.verify(&puzzle, &(solution + 1u32)));
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        assert_eq!(puzzle.solve(Some(&cancel), |_| {}), None);
+    }
+
+    #[test]
+    fn test_calibration() {
+        let calibration = Calibration::measure(256, Duration::from_millis(20)).unwrap();
+        assert!(calibration.squarings_per_second > 0.0);
+        let t = calibration.squarings_for(Duration::from_millis(10));
+        let (puzzle, _) = calibration.generate(Duration::from_millis(10)).unwrap();
+        assert_eq!(puzzle.t, t);
+    }
+}
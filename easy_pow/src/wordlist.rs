@@ -0,0 +1,255 @@
+//! Dictionary and hybrid search modes.
+//!
+//! A [`Wordlist`] is an [`Alphabet`] whose tokens are the lines of a file,
+//! optionally turned into several candidates each by [`Rule`]s. A
+//! plaintext_character_map of the wordlist alone searches its words, like
+//! hashcat's dictionary attack, and one that combines it with the positions
+//! of a mask searches every word followed or preceded by every plaintext of
+//! the mask, like hashcat's hybrid attacks.
+
+use std::{fs::File, io, ops::Deref, path::Path, sync::Arc};
+
+use memmap2::Mmap;
+
+use crate::{Alphabet, AnyAlphabet, PlaintextTemplate, Rule};
+
+#[derive(Debug)]
+enum Contents {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Deref for Contents {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Mapped(mmap) => mmap,
+            Self::Owned(bytes) => bytes,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Words {
+    contents: Contents,
+    /// `starts[i]` is where line `i` starts, followed by the end of the
+    /// contents past a final newline.
+    starts: Vec<usize>,
+}
+
+/// The words of a wordlist, one per line, with the rules applied to them.
+///
+/// Line breaks may be `\n` or `\r\n`. Clones share the words, so a wordlist
+/// is cheap to put into a plaintext_character_map.
+#[derive(Debug, Clone)]
+pub struct Wordlist {
+    words: Arc<Words>,
+    /// Each word yields one candidate per rule, all for one word before the
+    /// next. Empty means each word as it is.
+    rules: Arc<[Rule]>,
+}
+
+impl Wordlist {
+    /// Reads the file at `path` into memory.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::from_bytes(std::fs::read(path)?))
+    }
+
+    /// Memory-maps the file at `path`, so the words are paged in from the
+    /// file as searches read them instead of being copied onto the heap. The
+    /// start of every line is still kept in memory, 8 bytes per word.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this or any other
+    /// process, while the wordlist or any of its clones is alive. Otherwise
+    /// reading the words is undefined behavior.
+    pub unsafe fn open_mmap(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // An empty file cannot be mapped on every platform
+        if file.metadata()?.len() == 0 {
+            return Ok(Self::from_bytes(Vec::new()));
+        }
+        // SAFETY: the caller guarantees that the file stays unchanged
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(Self::new(Contents::Mapped(mmap)))
+    }
+
+    /// The lines of `contents`.
+    pub fn from_bytes(contents: impl Into<Vec<u8>>) -> Self {
+        Self::new(Contents::Owned(contents.into()))
+    }
+
+    fn new(contents: Contents) -> Self {
+        let mut starts = vec![0];
+        starts.extend(
+            contents
+                .iter()
+                .enumerate()
+                .filter(|&(_, &c)| c == b'\n')
+                .map(|(i, _)| i + 1),
+        );
+        if starts.last() != Some(&contents.len()) {
+            // The last line has no newline
+            starts.push(contents.len() + 1);
+        }
+        Self {
+            words: Arc::new(Words { contents, starts }),
+            rules: Arc::new([]),
+        }
+    }
+
+    /// The same words, each turned into one candidate per rule.
+    pub fn with_rules(mut self, rules: impl Into<Vec<Rule>>) -> Self {
+        self.rules = rules.into().into();
+        self
+    }
+
+    /// The number of lines.
+    pub fn word_count(&self) -> usize {
+        self.words.starts.len() - 1
+    }
+
+    /// The line at `index`, without its line break.
+    pub fn word(&self, index: usize) -> Option<&[u8]> {
+        let start = *self.words.starts.get(index)?;
+        let end = *self.words.starts.get(index + 1)? - 1;
+        let word = &self.words.contents[start..end];
+        Some(word.strip_suffix(b"\r").unwrap_or(word))
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+}
+
+impl Alphabet for Wordlist {
+    /// The number of candidates, words times rules.
+    fn len(&self) -> usize {
+        self.word_count() * self.rules.len().max(1)
+    }
+
+    #[inline]
+    fn push_token(&self, index: usize, plaintext: &mut Vec<u8>) {
+        let (word, rule) = match self.rules.len() {
+            0 => (index, None),
+            rules => (index / rules, Some(&self.rules[index % rules])),
+        };
+        let word = self.word(word).expect("token index out of range");
+        match rule {
+            Some(rule) => rule.apply(word, plaintext),
+            None => plaintext.extend_from_slice(word),
+        }
+    }
+}
+
+/// The plaintext_character_map of every word of `wordlist` followed by every
+/// plaintext of `mask`, like hashcat's `-a 6`.
+pub fn hybrid_wordlist_mask(wordlist: Wordlist, mask: &PlaintextTemplate) -> Vec<AnyAlphabet> {
+    let mut positions = vec![AnyAlphabet::from(wordlist)];
+    positions.extend(
+        mask.plaintext_character_map()
+            .iter()
+            .cloned()
+            .map(AnyAlphabet::from),
+    );
+    positions
+}
+
+/// The plaintext_character_map of every plaintext of `mask` followed by every
+/// word of `wordlist`, like hashcat's `-a 7`.
+pub fn hybrid_mask_wordlist(mask: &PlaintextTemplate, wordlist: Wordlist) -> Vec<AnyAlphabet> {
+    let mut positions: Vec<AnyAlphabet> = mask
+        .plaintext_character_map()
+        .iter()
+        .cloned()
+        .map(AnyAlphabet::from)
+        .collect();
+    positions.push(wordlist.into());
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hybrid_mask_wordlist, hybrid_wordlist_mask, Wordlist};
+    use crate::{Alphabet, Enumerator, PlaintextTemplate, Rule};
+
+    #[test]
+    fn test_wordlist() {
+        let path = std::env::temp_dir().join(format!("easy_pow_wordlist_{}", std::process::id()));
+        std::fs::write(&path, "admin\r\n\nroot").unwrap();
+        let wordlist = Wordlist::open(&path).unwrap();
+        // SAFETY: the file is only removed once the mapping is dropped
+        let mapped = unsafe { Wordlist::open_mmap(&path) }.unwrap();
+        assert_eq!(mapped.word(2), Some(&b"root"[..]));
+        drop(mapped);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(wordlist.word_count(), 3);
+        assert_eq!(wordlist.word(0), Some(&b"admin"[..]));
+        assert_eq!(wordlist.word(1), Some(&b""[..]));
+        assert_eq!(wordlist.word(2), Some(&b"root"[..]));
+        assert_eq!(wordlist.word(3), None);
+        assert_eq!(Wordlist::from_bytes("a\nb\n").word_count(), 2);
+        assert_eq!(Wordlist::from_bytes("").word_count(), 0);
+
+        let wordlist = wordlist.with_rules([Rule::default(), Rule::parse("c $1").unwrap()]);
+        assert_eq!(wordlist.len(), 6);
+        let map = [wordlist.clone()];
+        let candidates: Vec<Vec<u8>> = Enumerator::new(&map).collect();
+        assert_eq!(
+            candidates,
+            [&b"admin"[..], b"Admin1", b"", b"1", b"root", b"Root1"]
+        );
+
+        let wordlist = Wordlist::from_bytes("pw\nkey\n");
+        let digits = PlaintextTemplate::new().variable("01", 2);
+        let map = hybrid_wordlist_mask(wordlist.clone(), &digits);
+        let candidates: Vec<Vec<u8>> = Enumerator::new(&map).collect();
+        assert_eq!(candidates.len(), 8);
+        assert_eq!(candidates[1], b"pw01");
+        assert_eq!(candidates[7], b"key11");
+        let map = hybrid_mask_wordlist(&digits, wordlist);
+        let candidates: Vec<Vec<u8>> = Enumerator::new(&map).collect();
+        assert_eq!(candidates[1], b"00key");
+        assert_eq!(candidates[7], b"11key");
+    }
+
+    #[test]
+    #[cfg(any(feature = "backend-openssl", feature = "backend-rust-crypto"))]
+    fn test_search_wordlist() {
+        use crate::{search_by_hash_type, Hash, HashType, Md5, ThreadConfig};
+
+        let thread_config = ThreadConfig {
+            thread_count: Some(4),
+            deterministic: true,
+            ..ThreadConfig::default()
+        };
+        let words: String = (0..1000).map(|i| format!("word{i}\n")).collect();
+        let wordlist = Wordlist::from_bytes(words).with_rules([Rule::default(), Rule::leet()]);
+        let target = Md5::default().hash(b"w0rd7");
+        let report = search_by_hash_type(
+            HashType::Md5,
+            &target,
+            &[0xff; 16],
+            std::slice::from_ref(&wordlist),
+            &thread_config,
+        )
+        .unwrap();
+        let solution = report.solution().unwrap();
+        assert_eq!(solution.plaintext, b"w0rd7");
+        assert_eq!(solution.index, 15u32.into());
+
+        let digits = PlaintextTemplate::from_mask("?d?d", &[]).unwrap();
+        let target = Md5::default().hash(b"word99942");
+        let report = search_by_hash_type(
+            HashType::Md5,
+            &target,
+            &[0xff; 16],
+            &hybrid_wordlist_mask(wordlist, &digits),
+            &thread_config,
+        )
+        .unwrap();
+        assert_eq!(report.solution().unwrap().plaintext, b"word99942");
+    }
+}
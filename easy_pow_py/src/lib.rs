@@ -1,83 +1,218 @@
-use std::{
-    str::FromStr,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
-    time::Duration,
-};
+use std::{str::FromStr, time::Duration};
 
-use ::easy_pow::{search_by_hash_type, HashType, SearchResult, ThreadConfig};
+use ::easy_pow::{
+    parse_hcmask, spawn_search_by_hash_type, AnyAlphabet, HashType, PlaintextTemplate, PowError,
+    Rule, SearchHandle, SearchReport, Solution, ThreadConfig, Tokens, Wordlist,
+};
 use pyo3::{
-    exceptions::{asyncio::CancelledError, PyTypeError},
+    exceptions::{asyncio::CancelledError, PyOSError, PyRuntimeError, PyTypeError, PyValueError},
     prelude::*,
     types::PyBytes,
 };
 
-/// Formats the sum of two numbers as string.
+/// How often a running search wakes up to check for Python signals.
+const SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+fn pow_error_to_py(e: PowError) -> PyErr {
+    match e {
+        PowError::EmptyAlphabet { .. }
+        | PowError::TargetHashLength { .. }
+        | PowError::TargetHashMaskLength { .. }
+        | PowError::CollisionMaskBits { .. }
+        | PowError::CollisionKeyspace => PyValueError::new_err(e.to_string()),
+        PowError::Cancelled => CancelledError::new_err(e.to_string()),
+        PowError::Hash(_) | PowError::WorkerPanic { .. } | PowError::ChannelClosed => {
+            PyRuntimeError::new_err(e.to_string())
+        }
+    }
+}
+
+/// A plaintext_character_map given as a list of alphabets, as a list of
+/// token lists or as a hashcat mask.
+#[derive(FromPyObject)]
+enum CharacterMap<'a> {
+    Mask(&'a str),
+    Alphabets(Vec<&'a [u8]>),
+    Tokens(Vec<Vec<&'a [u8]>>),
+}
+
+fn thread_config() -> ThreadConfig {
+    ThreadConfig {
+        thread_count: None,
+        cancel: None,
+        deterministic: false,
+        progress: None,
+        priority: 0,
+    }
+}
+
+/// Waits for a search while checking for Python signals.
+fn wait(py: Python, mut handle: SearchHandle<Vec<u8>>) -> PyResult<Option<Solution<Vec<u8>>>> {
+    // Signals are only delivered to the main thread, so wake up now and then
+    // to check them. Dropping the handle on error cancels the search.
+    while !py.allow_threads(|| handle.join_timeout(SIGNAL_CHECK_INTERVAL).is_some()) {
+        py.check_signals()?;
+    }
+    handle
+        .join()
+        .and_then(SearchReport::into_solution)
+        .map_err(pow_error_to_py)
+}
+
+/// Searches a plaintext whose hash matches the target. The plaintexts are
+/// given by a list of alphabets, one per position, by a list of lists of
+/// tokens of any length, or by a hashcat mask whose custom charsets ?1 to ?4
+/// are `custom_charsets`.
 #[pyfunction(name = "easy_pow")]
+#[pyo3(signature = (hash_name, plaintext_character_map, target_hash, target_hash_mask, custom_charsets = Vec::new()))]
 fn easy_pow_py(
     py: Python,
     hash_name: &str,
-    plaintext_character_map: Vec<&[u8]>,
+    plaintext_character_map: CharacterMap,
     target_hash: &[u8],
     target_hash_mask: &[u8],
+    custom_charsets: Vec<&[u8]>,
 ) -> PyResult<Option<PyObject>> {
     let hash_type =
         HashType::from_str(hash_name).map_err(|e| PyTypeError::new_err(e.to_string()))?;
-    let plaintext_character_map: Vec<Vec<u8>> =
-        plaintext_character_map.iter().map(|a| a.to_vec()).collect();
-    let cancel = Arc::new(AtomicBool::new(false));
-    let thread_config = ThreadConfig {
-        thread_count: None,
-        cancel: Some(cancel.clone()),
-    };
     let target_hash = target_hash.to_vec();
     let target_hash_mask = target_hash_mask.to_vec();
-
-    let thread = std::thread::spawn(move || {
-        search_by_hash_type(
+    let handle = match plaintext_character_map {
+        CharacterMap::Mask(mask) => {
+            let plaintext_character_map = PlaintextTemplate::from_mask(mask, &custom_charsets)
+                .map_err(|e| PyValueError::new_err(e.to_string()))?
+                .build();
+            spawn_search_by_hash_type(
+                hash_type,
+                target_hash,
+                target_hash_mask,
+                plaintext_character_map,
+                thread_config(),
+            )
+        }
+        CharacterMap::Alphabets(alphabets) => spawn_search_by_hash_type(
             hash_type,
-            &target_hash,
-            &target_hash_mask,
-            &plaintext_character_map,
-            &thread_config,
-        )
-    });
+            target_hash,
+            target_hash_mask,
+            alphabets.iter().map(|a| a.to_vec()).collect(),
+            thread_config(),
+        ),
+        CharacterMap::Tokens(tokens) => spawn_search_by_hash_type(
+            hash_type,
+            target_hash,
+            target_hash_mask,
+            tokens.into_iter().map(Tokens::new).collect(),
+            thread_config(),
+        ),
+    };
+    let solution = wait(py, handle)?;
+    Ok(solution.map(|solution| PyBytes::new(py, &solution.plaintext).into()))
+}
 
-    loop {
-        std::thread::sleep(Duration::from_millis(5));
-        if let Err(e) = py.check_signals() {
-            cancel.store(true, Ordering::Relaxed);
-            return Err(e);
-        }
-        if thread.is_finished() {
-            break;
+/// Searches the masks of the contents of a .hcmask file in turn until one
+/// of them has a solution.
+#[pyfunction(name = "easy_pow_hcmask")]
+fn easy_pow_hcmask_py(
+    py: Python,
+    hash_name: &str,
+    hcmask: &str,
+    target_hash: &[u8],
+    target_hash_mask: &[u8],
+) -> PyResult<Option<PyObject>> {
+    let hash_type =
+        HashType::from_str(hash_name).map_err(|e| PyTypeError::new_err(e.to_string()))?;
+    let templates = parse_hcmask(hcmask).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    for template in templates {
+        let handle = spawn_search_by_hash_type(
+            hash_type.clone(),
+            target_hash.to_vec(),
+            target_hash_mask.to_vec(),
+            template.build(),
+            thread_config(),
+        );
+        if let Some(solution) = wait(py, handle)? {
+            return Ok(Some(PyBytes::new(py, &solution.plaintext).into()));
         }
     }
+    Ok(None)
+}
 
-    match thread
-        .join()
-        .map_err(|_| PyTypeError::new_err("Unexpected Error"))?
-    {
-        SearchResult::Found(result) => {
-            let result = PyBytes::new(py, &result);
-            Ok(Some(result.into()))
-        }
-        SearchResult::NotFound => Ok(None),
-        SearchResult::UnexpectedError => Err(PyTypeError::new_err("Unexpected Error")),
-        SearchResult::InvalidTargetHashLength => {
-            Err(PyTypeError::new_err("target_hash has invalid length"))
-        }
-        SearchResult::InvalidTargetHashMaskLength => {
-            Err(PyTypeError::new_err("target_hash_mask has invalid length"))
-        }
-    }
+/// Searches the words of the file at `wordlist_path`, one per line, each
+/// turned into one candidate per hashcat-style rule of `rules` and placed
+/// between the plaintexts of the masks `prefix_mask` and `suffix_mask`.
+#[pyfunction(name = "easy_pow_wordlist")]
+#[pyo3(signature = (hash_name, wordlist_path, target_hash, target_hash_mask, rules = Vec::new(), prefix_mask = "", suffix_mask = ""))]
+#[allow(clippy::too_many_arguments)]
+fn easy_pow_wordlist_py(
+    py: Python,
+    hash_name: &str,
+    wordlist_path: &str,
+    target_hash: &[u8],
+    target_hash_mask: &[u8],
+    rules: Vec<&str>,
+    prefix_mask: &str,
+    suffix_mask: &str,
+) -> PyResult<Option<PyObject>> {
+    let hash_type =
+        HashType::from_str(hash_name).map_err(|e| PyTypeError::new_err(e.to_string()))?;
+    let rules = rules
+        .into_iter()
+        .map(Rule::parse)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let wordlist = Wordlist::open(wordlist_path)
+        .map_err(|e| PyOSError::new_err(e.to_string()))?
+        .with_rules(rules);
+    let mask = |mask| {
+        PlaintextTemplate::from_mask(mask, &[])
+            .map(|template| template.build().into_iter().map(AnyAlphabet::from))
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    };
+    let mut plaintext_character_map: Vec<AnyAlphabet> = mask(prefix_mask)?.collect();
+    plaintext_character_map.push(wordlist.into());
+    plaintext_character_map.extend(mask(suffix_mask)?);
+    let handle = spawn_search_by_hash_type(
+        hash_type,
+        target_hash.to_vec(),
+        target_hash_mask.to_vec(),
+        plaintext_character_map,
+        thread_config(),
+    );
+    let solution = wait(py, handle)?;
+    Ok(solution.map(|solution| PyBytes::new(py, &solution.plaintext).into()))
+}
+
+/// The plaintext_character_map of `length` positions: `prefix`, then
+/// `charset` at every free position, then `suffix`.
+#[pyfunction]
+fn plaintext_template<'py>(
+    py: Python<'py>,
+    length: usize,
+    prefix: &[u8],
+    suffix: &[u8],
+    charset: &[u8],
+) -> PyResult<Vec<&'py PyBytes>> {
+    let free = length
+        .checked_sub(prefix.len() + suffix.len())
+        .ok_or_else(|| {
+            PyValueError::new_err(
+                "length must be greater than or equal to the sum of the lengths of prefix and suffix",
+            )
+        })?;
+    let map = PlaintextTemplate::new()
+        .fixed(prefix)
+        .variable(charset, free)
+        .fixed(suffix)
+        .build();
+    Ok(map.iter().map(|chars| PyBytes::new(py, chars)).collect())
 }
 
 /// A Python module implemented in Rust.
 #[pymodule]
 fn easy_pow(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(easy_pow_py, m)?)?;
+    m.add_function(wrap_pyfunction!(easy_pow_hcmask_py, m)?)?;
+    m.add_function(wrap_pyfunction!(easy_pow_wordlist_py, m)?)?;
+    m.add_function(wrap_pyfunction!(plaintext_template, m)?)?;
     Ok(())
 }
@@ -4,57 +4,210 @@ use magnus::{
     define_module, exception, function, prelude::*, value::qnil, Error, RArray, RString, Value,
 };
 
-use ::easy_pow::{search_by_hash_type, HashType, SearchResult, ThreadConfig};
+use ::easy_pow::{
+    parse_hcmask, search_by_hash_type, search_masks_by_hash_type, Alphabet, AnyAlphabet, HashType,
+    PlaintextTemplate, PowError, Rule, SearchReport, ThreadConfig, Tokens, Wordlist,
+};
+
+fn pow_error_to_rb(e: PowError) -> Error {
+    let class = match e {
+        PowError::EmptyAlphabet { .. }
+        | PowError::TargetHashLength { .. }
+        | PowError::TargetHashMaskLength { .. }
+        | PowError::CollisionMaskBits { .. }
+        | PowError::CollisionKeyspace => exception::arg_error(),
+        PowError::Cancelled => exception::interrupt(),
+        PowError::Hash(_) | PowError::WorkerPanic { .. } | PowError::ChannelClosed => {
+            exception::runtime_error()
+        }
+    };
+    Error::new(class, e.to_string())
+}
+
+fn thread_config() -> ThreadConfig {
+    ThreadConfig {
+        thread_count: None,
+        cancel: None,
+        deterministic: false,
+        progress: None,
+        priority: 0,
+    }
+}
 
+fn parse_hash_type(hash_name: &str) -> Result<HashType, Error> {
+    HashType::from_str(hash_name).map_err(|e| Error::new(exception::arg_error(), e.to_string()))
+}
+
+fn strings(array: RArray, message: &'static str) -> Result<Vec<Vec<u8>>, Error> {
+    let mut strings = Vec::with_capacity(array.len());
+    for i in 0..array.len() {
+        let string: RString = array
+            .entry(i as isize)
+            .map_err(|_e| Error::new(exception::arg_error(), message))?;
+        strings.push(string.to_bytes().to_vec());
+    }
+    Ok(strings)
+}
+
+fn solution_to_rb(report: Result<SearchReport<Vec<u8>>, PowError>) -> Result<Value, Error> {
+    let solution = report
+        .and_then(SearchReport::into_solution)
+        .map_err(pow_error_to_rb)?;
+    Ok(match solution {
+        Some(solution) => RString::from_slice(&solution.plaintext).as_value(),
+        None => qnil().as_value(),
+    })
+}
+
+fn search_map(
+    hash_type: HashType,
+    character_map: &[impl Alphabet + Clone + 'static],
+    target_hash: RString,
+    target_hash_mask: RString,
+) -> Result<Value, Error> {
+    let target_hash = target_hash.to_bytes().to_vec();
+    let target_hash_mask = target_hash_mask.to_bytes().to_vec();
+    solution_to_rb(search_by_hash_type(
+        hash_type,
+        &target_hash,
+        &target_hash_mask,
+        character_map,
+        &thread_config(),
+    ))
+}
+
+/// Searches with `plaintext_character_map` given as an Array of String, as
+/// an Array of Arrays of tokens of any length, or as a hashcat mask String.
 fn easy_pow(
     hash_name: String,
-    plaintext_character_map: RArray,
+    plaintext_character_map: Value,
     target_hash: RString,
     target_hash_mask: RString,
 ) -> Result<Value, Error> {
-    let hash_type = HashType::from_str(&hash_name)
-        .map_err(|e| Error::new(exception::arg_error(), e.to_string()))?;
-    let mut character_map: Vec<Vec<u8>> = vec![Vec::new(); plaintext_character_map.len()];
-
-    for i in 0..plaintext_character_map.len() {
-        let map: RString = plaintext_character_map.entry(i as isize).map_err(|_e| {
-            Error::new(
-                exception::arg_error(),
-                "plaintext_character_map requires List of String",
-            )
-        })?;
-        character_map[i] = map.to_bytes().to_vec();
+    let hash_type = parse_hash_type(&hash_name)?;
+    if let Some(mask) = RString::from_value(plaintext_character_map) {
+        let character_map = PlaintextTemplate::from_mask(mask.to_bytes(), &[])
+            .map_err(|e| Error::new(exception::arg_error(), e.to_string()))?
+            .build();
+        return search_map(hash_type, &character_map, target_hash, target_hash_mask);
+    }
+    let array = RArray::from_value(plaintext_character_map).ok_or_else(|| {
+        Error::new(
+            exception::arg_error(),
+            "plaintext_character_map requires List of String or a mask",
+        )
+    })?;
+    let tokens = (0..array.len()).any(|i| {
+        array
+            .entry::<Value>(i as isize)
+            .is_ok_and(|entry| RArray::from_value(entry).is_some())
+    });
+    if tokens {
+        let message = "plaintext_character_map requires List of List of String";
+        let mut character_map = Vec::with_capacity(array.len());
+        for i in 0..array.len() {
+            let position: RArray = array
+                .entry(i as isize)
+                .map_err(|_e| Error::new(exception::arg_error(), message))?;
+            character_map.push(Tokens::new(strings(position, message)?));
+        }
+        search_map(hash_type, &character_map, target_hash, target_hash_mask)
+    } else {
+        let character_map = strings(array, "plaintext_character_map requires List of String")?;
+        search_map(hash_type, &character_map, target_hash, target_hash_mask)
     }
+}
 
-    let thread_config = ThreadConfig {
-        thread_count: None,
-        cancel: None,
-    };
+/// Searches the plaintexts of a hashcat mask whose custom charsets ?1 to ?4
+/// are `custom_charsets`.
+fn search_mask(
+    hash_name: String,
+    mask: RString,
+    custom_charsets: RArray,
+    target_hash: RString,
+    target_hash_mask: RString,
+) -> Result<Value, Error> {
+    let hash_type = parse_hash_type(&hash_name)?;
+    let custom_charsets = strings(custom_charsets, "custom_charsets requires List of String")?;
+    let custom_charsets: Vec<&[u8]> = custom_charsets.iter().map(Vec::as_slice).collect();
+    let character_map = PlaintextTemplate::from_mask(mask.to_bytes(), &custom_charsets)
+        .map_err(|e| Error::new(exception::arg_error(), e.to_string()))?
+        .build();
+    search_map(hash_type, &character_map, target_hash, target_hash_mask)
+}
+
+/// Searches the masks of the contents of a .hcmask file in turn until one of
+/// them has a solution.
+fn search_hcmask(
+    hash_name: String,
+    hcmask: String,
+    target_hash: RString,
+    target_hash_mask: RString,
+) -> Result<Value, Error> {
+    let hash_type = parse_hash_type(&hash_name)?;
+    let templates =
+        parse_hcmask(&hcmask).map_err(|e| Error::new(exception::arg_error(), e.to_string()))?;
     let target_hash = target_hash.to_bytes().to_vec();
     let target_hash_mask = target_hash_mask.to_bytes().to_vec();
-
-    match search_by_hash_type(
+    solution_to_rb(search_masks_by_hash_type(
         hash_type,
         &target_hash,
         &target_hash_mask,
-        &character_map,
-        &thread_config,
-    ) {
-        SearchResult::Found(result) => Ok(RString::from_slice(&result).as_value()),
-        SearchResult::NotFound => Ok(qnil().as_value()),
-        SearchResult::UnexpectedError => Err(Error::new(
-            exception::standard_error(),
-            "Unexpected error occurred",
-        )),
-        SearchResult::InvalidTargetHashLength => Err(Error::new(
-            exception::arg_error(),
-            "target_hash has invalid length",
-        )),
-        SearchResult::InvalidTargetHashMaskLength => Err(Error::new(
-            exception::arg_error(),
-            "target_hash_mask has invalid length",
-        )),
+        &templates,
+        &thread_config(),
+    ))
+}
+
+/// Searches the words of the file at `path`, one per line, each turned into
+/// one candidate per hashcat-style rule of `rules` and placed between the
+/// plaintexts of the masks `prefix_mask` and `suffix_mask`.
+fn search_wordlist(
+    hash_name: String,
+    path: String,
+    rules: RArray,
+    prefix_mask: RString,
+    suffix_mask: RString,
+    target_hash: RString,
+    target_hash_mask: RString,
+) -> Result<Value, Error> {
+    let hash_type = parse_hash_type(&hash_name)?;
+    let rules = strings(rules, "rules requires List of String")?
+        .into_iter()
+        .map(Rule::parse)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| Error::new(exception::arg_error(), e.to_string()))?;
+    let wordlist = Wordlist::open(path)
+        .map_err(|e| Error::new(exception::io_error(), e.to_string()))?
+        .with_rules(rules);
+    let mask = |mask: RString| {
+        PlaintextTemplate::from_mask(mask.to_bytes(), &[])
+            .map(|template| template.build().into_iter().map(AnyAlphabet::from))
+            .map_err(|e| Error::new(exception::arg_error(), e.to_string()))
+    };
+    let mut character_map: Vec<AnyAlphabet> = mask(prefix_mask)?.collect();
+    character_map.push(wordlist.into());
+    character_map.extend(mask(suffix_mask)?);
+    search_map(hash_type, &character_map, target_hash, target_hash_mask)
+}
+
+/// The plaintext_character_map of `prefix`, then `length` positions of
+/// `chars`, then `suffix`.
+fn plaintext_template(
+    prefix: RString,
+    chars: RString,
+    length: usize,
+    suffix: RString,
+) -> Result<RArray, Error> {
+    let map = PlaintextTemplate::new()
+        .fixed(prefix.to_bytes())
+        .variable(chars.to_bytes(), length)
+        .fixed(suffix.to_bytes())
+        .build();
+    let array = RArray::with_capacity(map.len());
+    for chars in map {
+        array.push(RString::from_slice(&chars))?;
     }
+    Ok(array)
 }
 
 #[magnus::init]
@@ -64,4 +217,16 @@ fn init() {
     module
         .define_module_function("search", function!(easy_pow, 4))
         .unwrap();
+    module
+        .define_module_function("search_mask", function!(search_mask, 5))
+        .unwrap();
+    module
+        .define_module_function("search_hcmask", function!(search_hcmask, 4))
+        .unwrap();
+    module
+        .define_module_function("search_wordlist", function!(search_wordlist, 7))
+        .unwrap();
+    module
+        .define_module_function("plaintext_template", function!(plaintext_template, 4))
+        .unwrap();
 }
@@ -10,13 +10,31 @@ module EasyPow
     ['sha224', 224],
     ['sha256', 256],
     ['sha384', 384],
-    ['sha512', 512]
+    ['sha512', 512],
+    ['crc32', 32],
+    ['crc64', 64],
+    ['adler32', 32],
+    ['xxh32', 32],
+    ['xxhash32', 32],
+    ['xxh64', 64],
+    ['xxhash64', 64],
+    ['fnv1a32', 32],
+    ['fnv1a64', 64],
+    ['murmur3', 32]
   ]
 
+  # Output bits of a hash name or of the outermost hash of a pipeline such as
+  # 'sha256(hex(md5(x)) || "salt")'.
+  def hash_bits(hash)
+    name = hash.to_s.downcase[/\A\s*(\w+)/, 1]
+    len = @@hashes.select{|a|a[0] == name}[0]
+    len && len[1]
+  end
+
   def search_prefix(hash, bin, length, prefix, suffix = '', chars = 'abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789')
-    len = @@hashes.select{|a|a[0] == hash.to_s.downcase}[0]
+    len = hash_bits(hash)
     raise ArgumentError.new('Unknown hash type: %s' % [hash.to_s.downcase]) unless len
-    len = len[1] / 8
+    len = len / 8
     raise ArgumentError.new('Too long prefix') if bin.size > len * 8
     target = Array.new(len){0}
     mask = Array.new(len){0}
@@ -24,13 +42,13 @@ module EasyPow
       mask[i / 8] |= 1 << (7 - i % 8)
       target[i / 8] |= b.to_i << (7 - i % 8)
     end
-    search hash.to_s.downcase, prefix.chars + [chars] * length + suffix.chars, target.pack("C*"), mask.pack("C*")
+    search hash.to_s, plaintext_template(prefix, chars, length, suffix), target.pack("C*"), mask.pack("C*")
   end
 
   def search_suffix(hash, bin, length, prefix, suffix = '', chars = 'abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789')
-    len = @@hashes.select{|a|a[0] == hash.to_s.downcase}[0]
+    len = hash_bits(hash)
     raise ArgumentError.new('Unknown hash type: %s' % [hash.to_s.downcase]) unless len
-    len = len[1] / 8
+    len = len / 8
     raise ArgumentError.new('Too long prefix') if bin.size > len * 8
     target = Array.new(len){0}
     mask = Array.new(len){0}
@@ -38,7 +56,7 @@ module EasyPow
       mask[-(i / 8) - 1] |= 1 << (i % 8)
       target[-(i / 8) - 1] |= b.to_i << (i % 8)
     end
-    search hash.to_s.downcase, prefix.chars + [chars] * length + suffix.chars, target.pack("C*"), mask.pack("C*")
+    search hash.to_s, plaintext_template(prefix, chars, length, suffix), target.pack("C*"), mask.pack("C*")
   end
 
   def easy_pow(bits, socket = nil)
@@ -83,6 +101,6 @@ module EasyPow
     end
   end
 
-  module_function :search_prefix, :search_suffix
+  module_function :hash_bits, :search_prefix, :search_suffix
   module_function :easy_pow, :solve, :solve_line
 end
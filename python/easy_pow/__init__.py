@@ -33,7 +33,7 @@ def crate_plaintext_matrix(
     prefix: bytes = b"",
     suffix: bytes = b"",
     charset: bytes = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
-) -> List[bytearray]:
+) -> List[bytes]:
     if type(prefix) is not bytes:
         raise TypeError("prefix must be bytes")
     if type(suffix) is not bytes:
@@ -42,15 +42,7 @@ def crate_plaintext_matrix(
         raise TypeError("charset must be bytes")
     if type(length) is not int:
         raise TypeError("length must be int")
-    if len(prefix) + len(suffix) > length:
-        raise ValueError(
-            "length must be greater than or equal to the sum of the lengths of prefix and suffix"
-        )
-    return (
-        [prefix[i : i + 1] for i in range(len(prefix))]
-        + [charset] * (length - len(prefix) - len(suffix))
-        + [suffix[i : i + 1] for i in range(len(suffix))]
-    )
+    return plaintext_template(length, prefix, suffix, charset)
 
 
 __doc__ = easy_pow.__doc__
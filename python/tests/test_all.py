@@ -34,3 +34,57 @@ def test_create_plaintext_matrix_error(
         easy_pow.crate_plaintext_matrix(
             length, prefix=prefix, suffix=suffix, charset=charset
         )
+
+
+def test_easy_pow_mask():
+    target_hash, target_hash_mask = easy_pow.target_prefix(b"\x00", 16)
+    plaintext = easy_pow.easy_pow(
+        "md5", "ab?1?d?d", target_hash, target_hash_mask, [b"xyz"]
+    )
+    assert plaintext is not None
+    assert plaintext[:2] == b"ab"
+    assert plaintext[2:3] in b"xyz" and plaintext[3:].isdigit()
+
+    with pytest.raises(ValueError, match=r"unknown charset"):
+        easy_pow.easy_pow("md5", "?x", target_hash, target_hash_mask)
+    with pytest.raises(ValueError, match=r"line 2"):
+        easy_pow.easy_pow_hcmask("md5", "?d\n?9", target_hash, target_hash_mask)
+
+
+def test_easy_pow_tokens():
+    target_hash, target_hash_mask = easy_pow.target_prefix(b"\x00", 16)
+    digits = [bytes([c]) for c in b"0123456789"]
+    plaintext_character_map = [
+        [b"root", b"admin", "ゲスト".encode()],
+        [b"", b"!"],
+    ] + [digits] * 3
+    plaintext = easy_pow.easy_pow(
+        "md5", plaintext_character_map, target_hash, target_hash_mask
+    )
+    assert plaintext is not None
+    assert plaintext.rstrip(b"0123456789!") in (b"root", b"admin", "ゲスト".encode())
+
+
+def test_easy_pow_wordlist(tmp_path):
+    wordlist = tmp_path / "words.txt"
+    wordlist.write_text("".join(f"word{i}\n" for i in range(100)))
+    target_hash, target_hash_mask = easy_pow.target_prefix(b"\x00", 16)
+    plaintext = easy_pow.easy_pow_wordlist(
+        "md5",
+        str(wordlist),
+        target_hash,
+        target_hash_mask,
+        rules=[":", "c $!"],
+        suffix_mask="?d?d",
+    )
+    assert plaintext is not None
+    assert plaintext.lower().startswith(b"word")
+
+    with pytest.raises(ValueError, match=r"unknown rule function"):
+        easy_pow.easy_pow_wordlist(
+            "md5", str(wordlist), target_hash, target_hash_mask, rules=["x"]
+        )
+    with pytest.raises(OSError):
+        easy_pow.easy_pow_wordlist(
+            "md5", str(tmp_path / "missing"), target_hash, target_hash_mask
+        )